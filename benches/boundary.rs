@@ -0,0 +1,40 @@
+// Cost of an operation that creates and handles a number of errors before
+// one escapes, with a backtrace captured for every error or only by boundary!
+// for the one that escapes. Run with
+// `cargo bench --bench boundary --features backtrace-crate`.
+
+mod timing;
+
+use anyhow::{boundary, Error, Result};
+use std::env;
+use std::io;
+
+// Errors created and handled inside the operation, as by a retry loop.
+const HANDLED: usize = 10;
+
+fn io_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "oh no!")
+}
+
+fn capture_every_error() -> Result<()> {
+    for _ in 0..HANDLED {
+        drop(Error::new(io_error()).ensure_backtrace());
+    }
+    Err(Error::new(io_error()).ensure_backtrace())
+}
+
+fn capture_at_boundary() -> Result<()> {
+    for _ in 0..HANDLED {
+        drop(Error::new(io_error()));
+    }
+    boundary!(Err::<(), _>(io_error()))
+}
+
+fn main() {
+    // Capture at construction is turned off, so that only the errors asked
+    // for get a backtrace. This is read once, when the first error is created.
+    env::set_var("RUST_LIB_BACKTRACE", "0");
+
+    timing::bench("capture every error", capture_every_error);
+    timing::bench("capture at boundary!", capture_at_boundary);
+}
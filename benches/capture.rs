@@ -0,0 +1,18 @@
+// Cost of constructing an error with and without capturing a backtrace, run
+// with `cargo bench --bench capture --features backtrace-crate`.
+
+mod timing;
+
+use anyhow::anyhow;
+use std::env;
+
+fn main() {
+    // Capture at construction is turned off, as for an application that
+    // captures on demand. This is read once, when the first error is created.
+    env::set_var("RUST_LIB_BACKTRACE", "0");
+
+    timing::bench("construct", || anyhow!("oh no!"));
+    timing::bench("construct and capture a backtrace", || {
+        anyhow!("oh no!").ensure_backtrace()
+    });
+}
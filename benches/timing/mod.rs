@@ -0,0 +1,18 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// Call `f` repeatedly for about a second and print the average time per call,
+// including dropping what it returns.
+#[allow(clippy::incompatible_msrv)] // the benches need a newer compiler than the library
+pub fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    black_box(f());
+    let start = Instant::now();
+    let mut calls = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        for _ in 0..100 {
+            black_box(f());
+        }
+        calls += 100;
+    }
+    println!("{:<48} {:>12?}", name, start.elapsed() / calls);
+}
@@ -6,7 +6,7 @@ use std::process::{Command, ExitStatus};
 // This code exercises the surface area that we expect of the std Backtrace
 // type. If the current toolchain is able to compile it, we go ahead and use
 // backtrace in anyhow.
-const PROBE: &str = r#"
+const BACKTRACE_PROBE: &str = r#"
     #![feature(backtrace)]
     #![allow(dead_code)]
 
@@ -34,21 +34,118 @@ const PROBE: &str = r#"
     }
 "#;
 
+// This code exercises the generic member access API of std::error::Error. If
+// the current toolchain is able to compile it, anyhow forwards provide() calls
+// to the errors it wraps.
+const PROVIDE_PROBE: &str = r#"
+    #![feature(error_generic_member_access)]
+    #![allow(dead_code)]
+
+    use std::error::{Error, Request};
+    use std::fmt::{self, Display};
+
+    #[derive(Debug)]
+    struct E;
+
+    impl Display for E {
+        fn fmt(&self, _formatter: &mut fmt::Formatter) -> fmt::Result {
+            unimplemented!()
+        }
+    }
+
+    impl Error for E {
+        fn provide<'a>(&'a self, request: &mut Request<'a>) {
+            request.provide_ref(&0u8);
+        }
+    }
+
+    fn request<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a u8> {
+        std::error::request_ref::<u8>(error)
+    }
+"#;
+
+// The Error trait is available in core since Rust 1.81. If it is, anyhow
+// without the std feature builds on it rather than on a trait of its own.
+const ERROR_IN_CORE_PROBE: &str = r#"
+    #![no_std]
+    #![allow(dead_code)]
+
+    use core::error::Error;
+
+    fn source(error: &dyn Error) -> Option<&(dyn Error + 'static)> {
+        error.source()
+    }
+"#;
+
+// The #[track_caller] attribute and Location::caller are stable since Rust
+// 1.46. Where they are available, context_at! records the location of its
+// call.
+const TRACK_CALLER_PROBE: &str = r#"
+    #![no_std]
+    #![allow(dead_code)]
+
+    use core::panic::Location;
+
+    #[track_caller]
+    fn caller() -> &'static Location<'static> {
+        Location::caller()
+    }
+"#;
+
 fn main() {
+    // Declare every cfg set below, so that the unexpected_cfgs lint knows
+    // them whether or not they are set.
+    let cfgs = &[
+        "backtrace",
+        "backtrace_crate",
+        "provide",
+        "error_in_core",
+        "track_caller",
+    ];
+    for name in cfgs {
+        println!("cargo:rustc-check-cfg=cfg({})", name);
+    }
+
+    match compile_probe("track_caller", TRACK_CALLER_PROBE) {
+        Some(status) if status.success() => println!("cargo:rustc-cfg=track_caller"),
+        _ => {}
+    }
+
     if !cfg!(feature = "std") {
+        match compile_probe("error_in_core", ERROR_IN_CORE_PROBE) {
+            Some(status) if status.success() => println!("cargo:rustc-cfg=error_in_core"),
+            _ => {}
+        }
         return;
     }
-    match compile_probe() {
-        Some(status) if status.success() => println!("cargo:rustc-cfg=backtrace"),
+    // Backtraces cannot be captured on WebAssembly, so neither way of
+    // capturing them is set up there.
+    let wasm = cfg!(feature = "wasm")
+        || match env::var("CARGO_CFG_TARGET_ARCH") {
+            Ok(arch) => arch == "wasm32",
+            Err(_) => false,
+        };
+    if !wasm {
+        match compile_probe("backtrace", BACKTRACE_PROBE) {
+            Some(status) if status.success() => println!("cargo:rustc-cfg=backtrace"),
+            // Fall back to the backtrace crate if it was asked for.
+            _ if cfg!(feature = "backtrace-crate") => {
+                println!("cargo:rustc-cfg=backtrace_crate");
+            }
+            _ => {}
+        }
+    }
+    match compile_probe("provide", PROVIDE_PROBE) {
+        Some(status) if status.success() => println!("cargo:rustc-cfg=provide"),
         _ => {}
     }
 }
 
-fn compile_probe() -> Option<ExitStatus> {
+fn compile_probe(name: &str, probe: &str) -> Option<ExitStatus> {
     let rustc = env::var_os("RUSTC")?;
     let out_dir = env::var_os("OUT_DIR")?;
-    let probefile = Path::new(&out_dir).join("probe.rs");
-    fs::write(&probefile, PROBE).ok()?;
+    let probefile = Path::new(&out_dir).join(format!("probe_{}.rs", name));
+    fs::write(&probefile, probe).ok()?;
     Command::new(rustc)
         .arg("--edition=2018")
         .arg("--crate-name=anyhow_build")
@@ -0,0 +1,102 @@
+use crate::alloc::Vec;
+#[cfg(any(backtrace, backtrace_crate))]
+use crate::backtrace::Backtrace;
+use crate::{Error, StdError};
+use core::fmt::{self, Debug, Display};
+use core::iter::FromIterator;
+
+// The object of an aggregate error, holding the independent errors it groups.
+// Its members are not sources, so the chain of an aggregate ends with it.
+pub(crate) struct Aggregate(pub Vec<Error>);
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.0.len() == 1 { "" } else { "s" };
+        write!(f, "{} error{} occurred", self.0.len(), plural)
+    }
+}
+
+impl Debug for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Aggregate").field(&self.0).finish()
+    }
+}
+
+impl StdError for Aggregate {}
+
+impl Error {
+    /// Group several independent errors into one, for example every field
+    /// that failed validation, so that they can be reported together.
+    ///
+    /// The aggregate displays as the number of errors it holds. Its Debug
+    /// representation lists the Debug representation of each member as a
+    /// numbered block under an `Errors:` header, after the causes of the
+    /// aggregate if context was added to it. The members are not causes of
+    /// the aggregate: its [`chain`][Error::chain] ends with the aggregate
+    /// itself, and they are reached through [`members`][Error::members].
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error};
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    ///
+    /// let error = Error::aggregate(vec![
+    ///     anyhow!("name is empty"),
+    ///     anyhow!("age is negative"),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "2 errors occurred\n\nErrors:\n    0: name is empty\n    1: age is negative",
+    /// );
+    /// ```
+    pub fn aggregate(errors: Vec<Error>) -> Self {
+        let backtrace = backtrace!();
+        Error::from_std(Aggregate(errors), backtrace)
+    }
+
+    /// The errors grouped by [`aggregate`][Error::aggregate], if this error
+    /// or one of its causes is an aggregate.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error};
+    /// use std::num::ParseIntError;
+    ///
+    /// let parsed = "-".parse::<i32>().map_err(Error::new);
+    /// let error = Error::aggregate(vec![anyhow!("name is empty"), parsed.unwrap_err()])
+    ///     .context("invalid form");
+    ///
+    /// let members = error.members().unwrap();
+    /// assert_eq!(members.len(), 2);
+    /// assert!(members[1].is::<ParseIntError>());
+    /// ```
+    pub fn members(&self) -> Option<&[Error]> {
+        self.chain()
+            .find_map(|error| error.downcast_ref::<Aggregate>())
+            .map(|aggregate| aggregate.0.as_slice())
+    }
+}
+
+/// Collect errors into an [aggregate][Error::aggregate] of them.
+///
+/// Collecting no errors gives an aggregate with no members, which displays as
+/// `0 errors occurred`.
+///
+/// ```
+/// use anyhow::{anyhow, Error, Result};
+///
+/// let results: Vec<Result<u8>> = vec![Ok(1), Err(anyhow!("timed out")), Err(anyhow!("refused"))];
+/// let error: Error = results.into_iter().filter_map(Result::err).collect();
+///
+/// assert_eq!(error.to_string(), "2 errors occurred");
+/// ```
+impl FromIterator<Error> for Error {
+    fn from_iter<I>(errors: I) -> Self
+    where
+        I: IntoIterator<Item = Error>,
+    {
+        Error::aggregate(errors.into_iter().collect())
+    }
+}
@@ -1,17 +1,23 @@
 #[cfg(backtrace)]
 pub(crate) use std::backtrace::Backtrace;
 
-#[cfg(not(backtrace))]
+#[cfg(backtrace_crate)]
+pub(crate) use self::capture::Backtrace;
+
+#[cfg(not(any(backtrace, backtrace_crate)))]
 pub(crate) enum Backtrace {}
 
-#[cfg(backtrace)]
+#[cfg(feature = "backtrace-crate")]
+pub use self::filter::{set_backtrace_filter, BacktraceFrame};
+
+#[cfg(any(backtrace, backtrace_crate))]
 macro_rules! backtrace {
     () => {
         Some(Backtrace::capture())
     };
 }
 
-#[cfg(not(backtrace))]
+#[cfg(not(any(backtrace, backtrace_crate)))]
 macro_rules! backtrace {
     () => {
         None
@@ -28,9 +34,180 @@ macro_rules! backtrace_if_absent {
     };
 }
 
-#[cfg(all(feature = "std", not(backtrace)))]
+// Errors cannot provide a backtrace of the backtrace crate's kind, so one is
+// always captured.
+#[cfg(backtrace_crate)]
+macro_rules! backtrace_if_absent {
+    ($err:expr) => {
+        Some(Backtrace::capture())
+    };
+}
+
+#[cfg(all(
+    any(feature = "std", error_in_core),
+    not(any(backtrace, backtrace_crate))
+))]
 macro_rules! backtrace_if_absent {
     ($err:expr) => {
         None
     };
 }
+
+// Backtraces captured with the backtrace crate, for toolchains on which
+// std::backtrace is not usable by anyhow. Capture is controlled by the same
+// environment variables as std::backtrace::Backtrace::capture, and frames are
+// rendered in the same format.
+#[cfg(backtrace_crate)]
+mod capture {
+    use std::env;
+    use std::fmt::{self, Display};
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::Mutex;
+
+    pub(crate) struct Backtrace {
+        // Resolved to symbols only when first displayed. None if capture was
+        // disabled.
+        frames: Option<Mutex<::backtrace::Backtrace>>,
+    }
+
+    const UNDETECTED: u8 = 0;
+    const DISABLED: u8 = 1;
+    const ENABLED: u8 = 2;
+
+    static ENV: AtomicU8 = AtomicU8::new(UNDETECTED);
+
+    fn enabled() -> bool {
+        match ENV.load(Ordering::Relaxed) {
+            UNDETECTED => {
+                let enabled = match env::var_os("RUST_LIB_BACKTRACE") {
+                    Some(value) => value != "0",
+                    None => match env::var_os("RUST_BACKTRACE") {
+                        Some(value) => value != "0",
+                        None => false,
+                    },
+                };
+                ENV.store(if enabled { ENABLED } else { DISABLED }, Ordering::Relaxed);
+                enabled
+            }
+            state => state == ENABLED,
+        }
+    }
+
+    impl Backtrace {
+        pub(crate) fn capture() -> Self {
+            if enabled() {
+                Backtrace::force_capture()
+            } else {
+                Backtrace { frames: None }
+            }
+        }
+
+        pub(crate) fn force_capture() -> Self {
+            let frames = ::backtrace::Backtrace::new_unresolved();
+            Backtrace {
+                frames: Some(Mutex::new(frames)),
+            }
+        }
+
+        pub(crate) fn is_captured(&self) -> bool {
+            self.frames.is_some()
+        }
+    }
+
+    impl Display for Backtrace {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let frames = match &self.frames {
+                Some(frames) => frames,
+                None => return f.write_str("disabled backtrace"),
+            };
+            let mut frames = frames.lock().unwrap_or_else(|poison| poison.into_inner());
+            frames.resolve();
+
+            let filter = super::filter::current();
+            let shown = frames.frames().iter().filter(|frame| match &filter {
+                Some(filter) => filter(frame),
+                None => true,
+            });
+
+            f.write_str("stack backtrace:")?;
+            for (index, frame) in shown.enumerate() {
+                let symbols = frame.symbols();
+                if symbols.is_empty() {
+                    write!(f, "\n{:4}: <unknown>", index)?;
+                }
+                for (i, symbol) in symbols.iter().enumerate() {
+                    match symbol.name() {
+                        Some(name) if i == 0 => write!(f, "\n{:4}: {:#}", index, name)?,
+                        Some(name) => write!(f, "\n      {:#}", name)?,
+                        None if i == 0 => write!(f, "\n{:4}: <unknown>", index)?,
+                        None => f.write_str("\n      <unknown>")?,
+                    }
+                    if let (Some(file), Some(line)) = (symbol.filename(), symbol.lineno()) {
+                        write!(f, "\n             at {}:{}", file.display(), line)?;
+                        if let Some(column) = symbol.colno() {
+                            write!(f, ":{}", column)?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "backtrace-crate")]
+mod filter {
+    use crate::alloc::Box;
+    use crate::lazy::Lazy;
+    use std::sync::{Arc, RwLock};
+
+    pub use ::backtrace::BacktraceFrame;
+
+    type Filter = dyn Fn(&BacktraceFrame) -> bool + Send + Sync;
+
+    static FILTER: Lazy<RwLock<Option<Arc<Filter>>>> = lazy!(RwLock::new(None));
+
+    /// Choose which frames of a backtrace are shown when an `Error` is
+    /// printed, for example to hide the frames of anyhow itself and of the
+    /// runtime.
+    ///
+    /// Frames for which the filter returns false are left out, and the frames
+    /// that remain are numbered from 0 without gaps. By default every frame is
+    /// shown. Registering a filter replaces the previous one.
+    ///
+    /// This applies to backtraces captured through the `backtrace` crate,
+    /// which is what the `backtrace-crate` feature enables on a stable
+    /// compiler. The frames of a `std::backtrace::Backtrace` cannot be
+    /// inspected, so those are always shown whole.
+    ///
+    /// ```
+    /// anyhow::set_backtrace_filter(Box::new(|frame| {
+    ///     let noise = ["anyhow::", "core::", "std::rt::"];
+    ///     !frame.symbols().iter().any(|symbol| match symbol.name() {
+    ///         Some(name) => {
+    ///             let name = name.to_string();
+    ///             noise.iter().any(|prefix| name.starts_with(prefix))
+    ///         }
+    ///         None => false,
+    ///     })
+    /// }));
+    /// ```
+    pub fn set_backtrace_filter(filter: Box<Filter>) {
+        let mut global = FILTER
+            .get()
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner());
+        *global = Some(Arc::from(filter));
+    }
+
+    // Take a copy of the filter rather than holding the lock while it runs,
+    // so that the filter may replace itself.
+    #[cfg_attr(not(backtrace_crate), allow(dead_code))]
+    pub(super) fn current() -> Option<Arc<Filter>> {
+        FILTER
+            .get()
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone()
+    }
+}
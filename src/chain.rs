@@ -1,13 +1,16 @@
 use self::ChainState::*;
+use crate::alloc::Vec;
 use crate::StdError;
+use core::cell::Cell;
+use core::ptr;
 
-#[cfg(feature = "std")]
-use std::vec;
+#[cfg(any(feature = "std", error_in_core))]
+use crate::alloc::vec;
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 pub(crate) use crate::Chain;
 
-#[cfg(not(feature = "std"))]
+#[cfg(not(any(feature = "std", error_in_core)))]
 pub(crate) struct Chain<'a> {
     state: ChainState<'a>,
 }
@@ -16,33 +19,159 @@ pub(crate) struct Chain<'a> {
 pub(crate) enum ChainState<'a> {
     Linked {
         next: Option<&'a (dyn StdError + 'static)>,
+        seen: Seen<'a>,
+        // Number of errors remaining, counted on the first call to len().
+        remaining: Cell<Option<usize>>,
+        // Whether source() leads back to an error already yielded, found
+        // when the chain reaches its end, or when len() walks ahead to it.
+        cyclic: Cell<bool>,
     },
-    #[cfg(feature = "std")]
+    #[cfg(any(feature = "std", error_in_core))]
     Buffered {
         rest: vec::IntoIter<&'a (dyn StdError + 'static)>,
+        cyclic: bool,
     },
 }
 
 impl<'a> Chain<'a> {
     pub fn new(head: &'a (dyn StdError + 'static)) -> Self {
         Chain {
-            state: ChainState::Linked { next: Some(head) },
+            state: ChainState::Linked {
+                next: Some(head),
+                seen: Seen {
+                    inline: [None; INLINE_VISITED],
+                    count: 0,
+                    spilled: Vec::new(),
+                },
+                remaining: Cell::new(None),
+                cyclic: Cell::new(false),
+            },
+        }
+    }
+
+    /// The errors of the chain below the one it would yield next, which for a
+    /// chain from [`Error::chain`][crate::Error::chain] are the causes of the
+    /// error without the error itself.
+    ///
+    /// This is the same as `chain.skip(1)`, but says what the loop is for,
+    /// and keeps the chain's other abilities such as iterating in reverse.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("root cause").context("outer");
+    /// for cause in error.chain().sources() {
+    ///     assert_eq!(cause.to_string(), "root cause");
+    /// }
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn sources(mut self) -> Self {
+        self.next();
+        self
+    }
+
+    /// The errors of the chain that are of type `T`, in chain order.
+    ///
+    /// This is the same as `chain.filter_map(|e| e.downcast_ref::<T>())`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::io;
+    ///
+    /// fn read_config() -> Result<String> {
+    ///     let error = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    ///     Err(error).context("failed to read config")
+    /// }
+    ///
+    /// let error = read_config().unwrap_err();
+    /// for io_error in error.chain().filter_type::<io::Error>() {
+    ///     assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    /// }
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn filter_type<T>(self) -> impl Iterator<Item = &'a T>
+    where
+        T: StdError + 'static,
+    {
+        self.filter_map(|error| error.downcast_ref::<T>())
+    }
+
+    // Whether source() loops back on itself, in which case iteration stops
+    // before the first repeated error. This is found by the same walk that
+    // counts the errors for len(), so it costs nothing more once the length
+    // is known.
+    pub(crate) fn is_cyclic(&self) -> bool {
+        match &self.state {
+            Linked { cyclic, .. } => {
+                self.len();
+                cyclic.get()
+            }
+            #[cfg(any(feature = "std", error_in_core))]
+            Buffered { cyclic, .. } => *cyclic,
         }
     }
 }
 
+// The errors of chains up to this deep are remembered inline, so that only
+// deeper chains allocate.
+const INLINE_VISITED: usize = 8;
+
+// The errors yielded so far by a Chain. Since source() is implemented by
+// users, it may lead back to an error that was already yielded, and the chain
+// ends there instead of looping forever.
+#[derive(Clone)]
+pub(crate) struct Seen<'a> {
+    inline: [Option<&'a (dyn StdError + 'static)>; INLINE_VISITED],
+    count: usize,
+    spilled: Vec<&'a (dyn StdError + 'static)>,
+}
+
+impl<'a> Seen<'a> {
+    // Records `error` and returns true, or returns false if it was recorded
+    // before. Errors are compared by address and vtable, because an error and
+    // its source can share an address when one contains the other at offset
+    // zero. The same error reached through two different coercions may have
+    // two different vtables, so an error of a cycle can be yielded twice
+    // before the chain stops, but the chain always stops.
+    fn first_visit(&mut self, error: &'a (dyn StdError + 'static)) -> bool {
+        let inline = &self.inline[..self.count.min(INLINE_VISITED)];
+        let mut visited = inline.iter().flatten().chain(&self.spilled);
+        if visited.any(|&seen| ptr::eq(seen, error)) {
+            return false;
+        }
+        match self.inline.get_mut(self.count) {
+            Some(slot) => *slot = Some(error),
+            None => self.spilled.push(error),
+        }
+        self.count += 1;
+        true
+    }
+}
+
 impl<'a> Iterator for Chain<'a> {
     type Item = &'a (dyn StdError + 'static);
 
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.state {
-            Linked { next } => {
+            Linked {
+                next,
+                seen,
+                remaining,
+                cyclic,
+            } => {
                 let error = (*next)?;
+                if !seen.first_visit(error) {
+                    cyclic.set(true);
+                    return None;
+                }
                 *next = error.source();
+                if let Some(len) = remaining.get() {
+                    remaining.set(Some(len - 1));
+                }
                 Some(error)
             }
-            #[cfg(feature = "std")]
-            Buffered { rest } => rest.next(),
+            #[cfg(any(feature = "std", error_in_core))]
+            Buffered { rest, .. } => rest.next(),
         }
     }
 
@@ -52,22 +181,22 @@ impl<'a> Iterator for Chain<'a> {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl DoubleEndedIterator for Chain<'_> {
     fn next_back(&mut self) -> Option<Self::Item> {
         match &mut self.state {
-            Linked { mut next } => {
+            Linked { .. } => {
                 let mut rest = Vec::new();
-                while let Some(cause) = next {
-                    next = cause.source();
+                for cause in self.by_ref() {
                     rest.push(cause);
                 }
+                let cyclic = self.is_cyclic();
                 let mut rest = rest.into_iter();
                 let last = rest.next_back();
-                self.state = Buffered { rest };
+                self.state = Buffered { rest, cyclic };
                 last
             }
-            Buffered { rest } => rest.next_back(),
+            Buffered { rest, .. } => rest.next_back(),
         }
     }
 }
@@ -75,26 +204,49 @@ impl DoubleEndedIterator for Chain<'_> {
 impl ExactSizeIterator for Chain<'_> {
     fn len(&self) -> usize {
         match &self.state {
-            Linked { mut next } => {
+            Linked {
+                next,
+                seen,
+                remaining,
+                cyclic,
+            } => {
+                if let Some(len) = remaining.get() {
+                    return len;
+                }
+                let walk_cyclic = Cell::new(false);
+                let mut walk = Chain {
+                    state: Linked {
+                        next: *next,
+                        seen: seen.clone(),
+                        remaining: Cell::new(None),
+                        cyclic: walk_cyclic,
+                    },
+                };
                 let mut len = 0;
-                while let Some(cause) = next {
-                    next = cause.source();
+                while walk.next().is_some() {
                     len += 1;
                 }
+                match &walk.state {
+                    Linked { cyclic: walked, .. } => cyclic.set(walked.get()),
+                    #[cfg(any(feature = "std", error_in_core))]
+                    Buffered { .. } => {}
+                }
+                remaining.set(Some(len));
                 len
             }
-            #[cfg(feature = "std")]
-            Buffered { rest } => rest.len(),
+            #[cfg(any(feature = "std", error_in_core))]
+            Buffered { rest, .. } => rest.len(),
         }
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl Default for Chain<'_> {
     fn default() -> Self {
         Chain {
             state: ChainState::Buffered {
                 rest: Vec::new().into_iter(),
+                cyclic: false,
             },
         }
     }
@@ -0,0 +1,165 @@
+#[cfg(feature = "termcolor")]
+use crate::fmt::strip_prefix;
+use core::fmt::{self, Display};
+
+#[cfg(feature = "color")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "termcolor")]
+use std::io;
+#[cfg(feature = "termcolor")]
+use termcolor::{Color, ColorSpec, WriteColor};
+
+#[derive(Copy, Clone)]
+pub(crate) enum Style {
+    Bold,
+    Dimmed,
+    #[cfg_attr(not(any(backtrace, backtrace_crate)), allow(dead_code))]
+    Header,
+}
+
+impl Style {
+    #[cfg(feature = "termcolor")]
+    const ALL: [Style; 3] = [Style::Bold, Style::Dimmed, Style::Header];
+
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Dimmed => "2",
+            Style::Header => "1;35",
+        }
+    }
+
+    #[cfg(feature = "termcolor")]
+    fn spec(self) -> ColorSpec {
+        let mut spec = ColorSpec::new();
+        match self {
+            Style::Bold => spec.set_bold(true),
+            Style::Dimmed => spec.set_dimmed(true),
+            Style::Header => spec.set_bold(true).set_fg(Some(Color::Magenta)),
+        };
+        spec
+    }
+
+    pub(crate) fn paint<T>(self, value: T, color: bool) -> Painted<T> {
+        Painted {
+            value,
+            style: self,
+            color,
+        }
+    }
+}
+
+pub(crate) struct Painted<T> {
+    value: T,
+    style: Style,
+    color: bool,
+}
+
+impl<T> Display for Painted<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.color {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.style.code(), self.value)
+        } else {
+            Display::fmt(&self.value, f)
+        }
+    }
+}
+
+// A terminal hyperlink to `url` showing `text`, written as an OSC 8 escape
+// sequence when colors are on and as the text alone otherwise.
+pub(crate) struct Hyperlink<'a, T> {
+    pub url: &'a str,
+    pub text: T,
+    pub color: bool,
+}
+
+impl<T> Display for Hyperlink<'_, T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.color {
+            write!(f, "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", self.url, self.text)
+        } else {
+            Display::fmt(&self.text, f)
+        }
+    }
+}
+
+#[cfg(feature = "color")]
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Turn ANSI colors in the Debug representation of `Error` on or off.
+///
+/// Colors are off by default, so that errors formatted into strings, log
+/// lines or redirected output stay plain. A program that prints its errors to
+/// a terminal can turn them on here, or color a single error with
+/// [`Error::debug_colored`][crate::Error::debug_colored].
+///
+/// With colors on, the source paths in backtraces and the URLs attached with
+/// [`Error::with_help_url`][crate::Error::with_help_url] are also written as
+/// terminal hyperlinks.
+#[cfg(feature = "color")]
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+#[cfg(feature = "color")]
+pub(crate) fn enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "color"))]
+pub(crate) fn enabled() -> bool {
+    false
+}
+
+// Write output that was rendered with colors to a termcolor sink, replacing
+// the escape sequences written by Painted with the sink's own way of setting
+// colors. Hyperlinks are reduced to their text, since not every sink can
+// show them.
+#[cfg(feature = "termcolor")]
+pub(crate) fn write_color(w: &mut dyn WriteColor, mut rendered: &str) -> io::Result<()> {
+    while let Some(start) = rendered.find('\x1b') {
+        w.write_all(&rendered.as_bytes()[..start])?;
+        let escape = &rendered[start + 1..];
+        if let Some(link) = strip_prefix(escape, "]8;") {
+            if let Some(end) = link.find("\x1b\\") {
+                rendered = &link[end + 2..];
+                continue;
+            }
+        }
+        let escape = match strip_prefix(escape, "[") {
+            Some(escape) => escape,
+            None => {
+                w.write_all(b"\x1b")?;
+                rendered = escape;
+                continue;
+            }
+        };
+        if let Some(rest) = strip_prefix(escape, "0m") {
+            w.reset()?;
+            rendered = rest;
+            continue;
+        }
+        let style = Style::ALL.iter().find_map(|style| {
+            let rest = strip_prefix(strip_prefix(escape, style.code())?, "m")?;
+            Some((style, rest))
+        });
+        match style {
+            Some((style, rest)) => {
+                w.set_color(&style.spec())?;
+                rendered = rest;
+            }
+            None => {
+                w.write_all(b"\x1b[")?;
+                rendered = escape;
+            }
+        }
+    }
+    w.write_all(rendered.as_bytes())
+}
@@ -1,11 +1,18 @@
-use crate::error::ContextError;
+use crate::alloc::{String, Vec};
+use crate::error::{ContextError, ReplacedHead};
 use crate::{Context, Error, StdError};
 use core::convert::Infallible;
 use core::fmt::{self, Debug, Display, Write};
+use core::panic::Location;
 
+#[cfg(backtrace_crate)]
+use crate::backtrace::Backtrace;
 #[cfg(backtrace)]
 use std::backtrace::Backtrace;
 
+#[cfg(provide)]
+use std::error::Request;
+
 mod ext {
     use super::*;
 
@@ -13,19 +20,45 @@ mod ext {
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static;
+
+        fn ext_context_at<C>(
+            self,
+            context: C,
+            location: Option<&'static Location<'static>>,
+        ) -> Error
+        where
+            C: Display + Send + Sync + 'static;
+
+        fn ext_into(self) -> Error;
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(any(feature = "std", error_in_core))]
     impl<E> StdError for E
     where
-        E: std::error::Error + Send + Sync + 'static,
+        E: crate::StdError + Send + Sync + 'static,
     {
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static,
         {
             let backtrace = backtrace_if_absent!(self);
-            Error::from_context(context, self, backtrace)
+            Error::from_context(context, self, None, backtrace)
+        }
+
+        fn ext_context_at<C>(
+            self,
+            context: C,
+            location: Option<&'static Location<'static>>,
+        ) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            let backtrace = backtrace_if_absent!(self);
+            Error::from_context(context, self, location, backtrace)
+        }
+
+        fn ext_into(self) -> Error {
+            Error::from(self)
         }
     }
 
@@ -36,6 +69,21 @@ mod ext {
         {
             self.context(context)
         }
+
+        fn ext_context_at<C>(
+            self,
+            context: C,
+            location: Option<&'static Location<'static>>,
+        ) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            self.context_located(context, location)
+        }
+
+        fn ext_into(self) -> Error {
+            self
+        }
     }
 }
 
@@ -57,6 +105,43 @@ where
     {
         self.map_err(|error| error.ext_context(context()))
     }
+
+    fn context_capture<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.map_err(|error| error.ext_context(context).ensure_backtrace())
+    }
+
+    fn context_if<C, F>(self, condition: bool, context: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| {
+            if condition {
+                error.ext_context(context())
+            } else {
+                error.ext_into()
+            }
+        })
+    }
+
+    fn context_all<I>(self, lines: I) -> Result<T, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.map_err(|error| error.ext_context(ContextLines::new(lines)))
+    }
+
+    #[cfg(any(feature = "std", error_in_core))]
+    fn with_context_err<C, F>(self, context: F) -> Result<T, Error>
+    where
+        C: StdError + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| error.ext_context(context()))
+    }
 }
 
 /// ```
@@ -94,6 +179,58 @@ impl<T> Context<T, Infallible> for Option<T> {
     {
         self.ok_or_else(|| Error::from_display(context(), backtrace!()))
     }
+
+    fn context_capture<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.ok_or_else(|| Error::from_display(context, backtrace!()).ensure_backtrace())
+    }
+
+    fn context_if<C, F>(self, _condition: bool, context: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        // There is no underlying error to pass through, so the context is
+        // always the error.
+        self.with_context(context)
+    }
+
+    fn context_all<I>(self, lines: I) -> Result<T, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.ok_or_else(|| Error::from_display(ContextLines::new(lines), backtrace!()))
+    }
+
+    #[cfg(any(feature = "std", error_in_core))]
+    fn with_context_err<C, F>(self, context: F) -> Result<T, Error>
+    where
+        C: StdError + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.ok_or_else(|| Error::new(context()))
+    }
+}
+
+// Not public API. Called by context_at! with the location of the call, where
+// the compiler can provide it.
+#[doc(hidden)]
+#[cfg_attr(track_caller, track_caller)]
+pub fn context_at<T, E, C, F>(result: Result<T, E>, f: F) -> Result<T, Error>
+where
+    E: ext::StdError + Send + Sync + 'static,
+    C: Display + Send + Sync + 'static,
+    F: FnOnce() -> C,
+{
+    // The build script sets track_caller only where Location::caller exists.
+    #[cfg(track_caller)]
+    #[allow(clippy::incompatible_msrv)]
+    let location = Some(Location::caller());
+    #[cfg(not(track_caller))]
+    let location = None;
+    result.map_err(|error| error.ext_context_at(f(), location))
 }
 
 impl<C, E> Debug for ContextError<C, E>
@@ -131,6 +268,11 @@ where
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         Some(&self.error)
     }
+
+    #[cfg(provide)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        self.error.provide(request);
+    }
 }
 
 impl<C> StdError for ContextError<C, Error>
@@ -145,6 +287,68 @@ where
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         Some(self.error.inner.error())
     }
+
+    #[cfg(provide)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        self.error.inner.error().provide(request);
+    }
+}
+
+impl<C> Debug for ContextError<C, ReplacedHead>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Error");
+        debug.field("context", &Quoted(&self.context));
+        if let Some(source) = self.source() {
+            debug.field("source", &source);
+        }
+        debug.finish()
+    }
+}
+
+impl<C> StdError for ContextError<C, ReplacedHead>
+where
+    C: Display,
+{
+    #[cfg(backtrace)]
+    fn backtrace(&self) -> Option<&Backtrace> {
+        Some(self.error.0.backtrace())
+    }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.0.inner.error().source()
+    }
+
+    #[cfg(provide)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        self.error.0.inner.error().provide(request);
+    }
+}
+
+// The context added by context_all, displayed one line after another.
+struct ContextLines(Vec<String>);
+
+impl ContextLines {
+    fn new<I>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        ContextLines(lines.into_iter().collect())
+    }
+}
+
+impl Display for ContextLines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            f.write_str(line)?;
+        }
+        Ok(())
+    }
 }
 
 struct Quoted<C>(C);
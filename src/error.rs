@@ -1,13 +1,18 @@
-use crate::alloc::Box;
+use crate::alloc::{Box, String, Vec};
 use crate::backtrace::Backtrace;
 use crate::chain::Chain;
+use crate::extensions::Extensions;
+use crate::fmt::ErrorFormatter;
+#[cfg(backtrace)]
+use crate::snapshot::BacktraceSnapshot;
 use crate::{Error, StdError};
 use core::any::TypeId;
-use core::fmt::{self, Debug, Display};
+use core::fmt::{self, Debug, Display, Write};
 use core::mem::{self, ManuallyDrop};
+use core::panic::Location;
 use core::ptr::{self, NonNull};
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 use core::ops::{Deref, DerefMut};
 
 impl Error {
@@ -18,7 +23,7 @@ impl Error {
     ///
     /// If the error type does not provide a backtrace, a backtrace will be
     /// created here to ensure that a backtrace exists.
-    #[cfg(feature = "std")]
+    #[cfg(any(feature = "std", error_in_core))]
     pub fn new<E>(error: E) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -27,6 +32,35 @@ impl Error {
         Error::from_std(error, backtrace)
     }
 
+    /// Create a new error object from an error that is already boxed, without
+    /// boxing it a second time.
+    ///
+    /// If the error does not provide a backtrace, a backtrace will be created
+    /// here to ensure that a backtrace exists.
+    ///
+    /// The concrete type of the boxed error is not known to anyhow, so the
+    /// resulting error downcasts to `Box<dyn Error + Send + Sync>`, which can
+    /// in turn be downcast to the original type.
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::error::Error as StdError;
+    /// use std::io;
+    ///
+    /// let boxed: Box<dyn StdError + Send + Sync> =
+    ///     Box::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    /// let error = Error::from_boxed(boxed);
+    ///
+    /// let boxed = error.downcast::<Box<dyn StdError + Send + Sync>>().unwrap();
+    /// let io_error = boxed.downcast::<io::Error>().unwrap();
+    /// assert_eq!(io_error.kind(), io::ErrorKind::Other);
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn from_boxed(boxed: Box<dyn StdError + Send + Sync + 'static>) -> Self {
+        let backtrace = backtrace_if_absent!(boxed);
+        Error::from_boxed_error(boxed, backtrace)
+    }
+
     /// Create a new error object from a printable error message.
     ///
     /// If the argument implements std::error::Error, prefer `Error::new`
@@ -35,6 +69,9 @@ impl Error {
     /// now or in the future, use `anyhow!(err)` which handles either way
     /// correctly.
     ///
+    /// An owned `String` message is stored as it is, whereas
+    /// `anyhow!("{}", message)` would format it into a new string first.
+    ///
     /// `Error::msg("...")` is equivalent to `anyhow!("...")` but occasionally
     /// convenient in places where a function is preferable over a macro, such
     /// as iterator or stream combinators:
@@ -71,23 +108,13 @@ impl Error {
         Error::from_adhoc(message, backtrace!())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(any(feature = "std", error_in_core))]
     pub(crate) fn from_std<E>(error: E, backtrace: Option<Backtrace>) -> Self
     where
         E: StdError + Send + Sync + 'static,
     {
-        let vtable = &ErrorVTable {
-            object_drop: object_drop::<E>,
-            object_ref: object_ref::<E>,
-            #[cfg(feature = "std")]
-            object_mut: object_mut::<E>,
-            object_boxed: object_boxed::<E>,
-            object_downcast: object_downcast::<E>,
-            object_drop_rest: object_drop_front::<E>,
-        };
-
         // Safety: passing vtable that operates on the right type E.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, std_vtable::<E>(), backtrace, Extensions::new()) }
     }
 
     pub(crate) fn from_adhoc<M>(message: M, backtrace: Option<Backtrace>) -> Self
@@ -99,16 +126,20 @@ impl Error {
         let vtable = &ErrorVTable {
             object_drop: object_drop::<MessageError<M>>,
             object_ref: object_ref::<MessageError<M>>,
-            #[cfg(feature = "std")]
+            #[cfg(any(feature = "std", error_in_core))]
             object_mut: object_mut::<MessageError<M>>,
             object_boxed: object_boxed::<MessageError<M>>,
             object_downcast: object_downcast::<M>,
             object_drop_rest: object_drop_front::<M>,
+            object_context: no_context,
+            object_type_id: object_type_id::<M>,
+            object_peel: no_peel,
+            object_layer_mut: no_layer_mut,
         };
 
         // Safety: MessageError is repr(transparent) so it is okay for the
         // vtable to allow casting the MessageError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, Extensions::new()) }
     }
 
     pub(crate) fn from_display<M>(message: M, backtrace: Option<Backtrace>) -> Self
@@ -120,42 +151,59 @@ impl Error {
         let vtable = &ErrorVTable {
             object_drop: object_drop::<DisplayError<M>>,
             object_ref: object_ref::<DisplayError<M>>,
-            #[cfg(feature = "std")]
+            #[cfg(any(feature = "std", error_in_core))]
             object_mut: object_mut::<DisplayError<M>>,
             object_boxed: object_boxed::<DisplayError<M>>,
             object_downcast: object_downcast::<M>,
             object_drop_rest: object_drop_front::<M>,
+            object_context: no_context,
+            object_type_id: object_type_id::<M>,
+            object_peel: no_peel,
+            object_layer_mut: no_layer_mut,
         };
 
         // Safety: DisplayError is repr(transparent) so it is okay for the
         // vtable to allow casting the DisplayError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, Extensions::new()) }
     }
 
-    #[cfg(feature = "std")]
-    pub(crate) fn from_context<C, E>(context: C, error: E, backtrace: Option<Backtrace>) -> Self
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn from_context<C, E>(
+        context: C,
+        error: E,
+        location: Option<&'static Location<'static>>,
+        backtrace: Option<Backtrace>,
+    ) -> Self
     where
         C: Display + Send + Sync + 'static,
         E: StdError + Send + Sync + 'static,
     {
-        let error: ContextError<C, E> = ContextError { context, error };
+        let error: ContextError<C, E> = ContextError {
+            context,
+            error,
+            location,
+        };
 
         let vtable = &ErrorVTable {
             object_drop: object_drop::<ContextError<C, E>>,
             object_ref: object_ref::<ContextError<C, E>>,
-            #[cfg(feature = "std")]
+            #[cfg(any(feature = "std", error_in_core))]
             object_mut: object_mut::<ContextError<C, E>>,
             object_boxed: object_boxed::<ContextError<C, E>>,
             object_downcast: context_downcast::<C, E>,
             object_drop_rest: context_drop_rest::<C, E>,
+            object_context: context_layer::<C, E>,
+            object_type_id: context_type_id::<C, E>,
+            object_peel: context_peel::<C, E>,
+            object_layer_mut: context_layer_mut::<C, E>,
         };
 
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, Extensions::new()) }
     }
 
-    #[cfg(feature = "std")]
-    pub(crate) fn from_boxed(
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn from_boxed_error(
         error: Box<dyn StdError + Send + Sync>,
         backtrace: Option<Backtrace>,
     ) -> Self {
@@ -164,16 +212,20 @@ impl Error {
         let vtable = &ErrorVTable {
             object_drop: object_drop::<BoxedError>,
             object_ref: object_ref::<BoxedError>,
-            #[cfg(feature = "std")]
+            #[cfg(any(feature = "std", error_in_core))]
             object_mut: object_mut::<BoxedError>,
             object_boxed: object_boxed::<BoxedError>,
             object_downcast: object_downcast::<Box<dyn StdError + Send + Sync>>,
             object_drop_rest: object_drop_front::<Box<dyn StdError + Send + Sync>>,
+            object_context: no_context,
+            object_type_id: object_type_id::<Box<dyn StdError + Send + Sync>>,
+            object_peel: no_peel,
+            object_layer_mut: no_layer_mut,
         };
 
         // Safety: BoxedError is repr(transparent) so it is okay for the vtable
         // to allow casting to Box<dyn StdError + Send + Sync>.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, Extensions::new()) }
     }
 
     // Takes backtrace as argument rather than capturing it here so that the
@@ -185,6 +237,27 @@ impl Error {
         error: E,
         vtable: &'static ErrorVTable,
         backtrace: Option<Backtrace>,
+        extensions: Extensions,
+    ) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let error = Error::assemble(error, vtable, backtrace, extensions);
+        error.observed()
+    }
+
+    // Like construct, but for an error that already existed in another form,
+    // such as the error inside a context layer that is being taken apart. The
+    // error hook has seen it once already, so it is not run again, and no new
+    // origin is recorded.
+    //
+    // Unsafe because the given vtable must have sensible behavior on the error
+    // value of type E.
+    unsafe fn assemble<E>(
+        error: E,
+        vtable: &'static ErrorVTable,
+        backtrace: Option<Backtrace>,
+        extensions: Extensions,
     ) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -192,6 +265,9 @@ impl Error {
         let inner = Box::new(ErrorImpl {
             vtable,
             backtrace,
+            extensions,
+            #[cfg(feature = "track-caller")]
+            creation_location: None,
             _object: error,
         });
         // Erase the concrete type of E from the compile-time type system. This
@@ -205,6 +281,43 @@ impl Error {
         Error { inner }
     }
 
+    // Record where a newly created error came from and show it to the error
+    // hook.
+    #[cfg_attr(
+        not(any(feature = "thread-origin", feature = "timestamp")),
+        allow(unused_mut)
+    )]
+    fn observed(mut self) -> Self {
+        #[cfg(any(feature = "thread-origin", feature = "timestamp"))]
+        self.record_origin();
+        #[cfg(feature = "std")]
+        crate::hook::observe(&self);
+        self
+    }
+
+    // Record the call site of the anyhow! or bail! that created this error,
+    // unless it already has one. An error passed to anyhow! that already has
+    // context was created somewhere else, so it is left alone.
+    #[cfg(feature = "track-caller")]
+    #[track_caller]
+    #[allow(clippy::incompatible_msrv)] // the track-caller feature needs 1.46
+    pub(crate) fn at_caller(mut self) -> Self {
+        let inner: &ErrorImpl<()> = &self.inner;
+        // Use vtable to find whether this is a context error.
+        if unsafe { (inner.vtable.object_context)(inner) }.is_none() {
+            self.inner
+                .creation_location
+                .get_or_insert(Location::caller());
+        }
+        self
+    }
+
+    #[cfg(not(feature = "track-caller"))]
+    #[inline]
+    pub(crate) fn at_caller(self) -> Self {
+        self
+    }
+
     /// Wrap the error value with additional context.
     ///
     /// For attaching context to a `Result` as it is propagated, the
@@ -259,87 +372,1081 @@ impl Error {
     ///     })
     /// }
     /// ```
-    pub fn context<C>(self, context: C) -> Self
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.context_located(context, None)
+    }
+
+    /// Wrap the error value with additional context in place, like
+    /// [`context`][Error::context] but through a mutable reference.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let mut error = anyhow!("disk full");
+    /// for step in ["write block", "flush journal"] {
+    ///     error.push_context(step);
+    /// }
+    /// assert_eq!(error.to_string(), "flush journal");
+    /// assert_eq!(error.chain().count(), 3);
+    /// ```
+    pub fn push_context<C>(&mut self, context: C)
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.replace_with(|error| error.context(context));
+    }
+
+    // Replace self with the error computed from it by f.
+    fn replace_with<F>(&mut self, f: F)
+    where
+        F: FnOnce(Error) -> Error,
+    {
+        // Panics while already unwinding, which aborts the process.
+        struct Abort;
+        impl Drop for Abort {
+            fn drop(&mut self) {
+                panic!("panicked while adding context to an error in place");
+            }
+        }
+
+        // Safety: the error read out of self is written back, wrapped in
+        // context, before self is used again. If wrapping it unwinds, which
+        // only a panicking error hook can cause, the guard aborts rather than
+        // let the moved-out error be dropped a second time.
+        let guard = Abort;
+        unsafe {
+            let error = ptr::read(self);
+            ptr::write(self, f(error));
+        }
+        mem::forget(guard);
+    }
+
+    pub(crate) fn context_located<C>(
+        self,
+        context: C,
+        location: Option<&'static Location<'static>>,
+    ) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.wrap_context(context, location).observed()
+    }
+
+    // Add a layer of context without showing the result to the error hook,
+    // for when an existing context layer is only being rebuilt.
+    fn wrap_context<C>(mut self, context: C, location: Option<&'static Location<'static>>) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        // Extensions always live on the outermost error.
+        let extensions = self.inner.extensions.take();
+
+        // So does a backtrace from the backtrace crate, as it cannot be found
+        // through source() like a std one.
+        #[cfg(backtrace_crate)]
+        let backtrace = self.inner.backtrace.take();
+
+        let error: ContextError<C, Error> = ContextError {
+            context,
+            error: self,
+            location,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, Error>>,
+            object_ref: object_ref::<ContextError<C, Error>>,
+            #[cfg(any(feature = "std", error_in_core))]
+            object_mut: object_mut::<ContextError<C, Error>>,
+            object_boxed: object_boxed::<ContextError<C, Error>>,
+            object_downcast: context_chain_downcast::<C>,
+            object_drop_rest: context_chain_drop_rest::<C>,
+            object_context: context_chain_layer::<C>,
+            object_type_id: context_chain_type_id::<C>,
+            object_peel: context_chain_peel::<C>,
+            object_layer_mut: context_chain_layer_mut::<C>,
+        };
+
+        // As the cause is anyhow::Error, we already have a backtrace for it.
+        #[cfg(not(backtrace_crate))]
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::assemble(error, vtable, backtrace, extensions) }
+    }
+
+    /// Replace the outermost message of this error with one computed from it,
+    /// for example to redact a path before the error crosses an API boundary.
+    ///
+    /// The rest of the chain, the backtrace and the extensions are kept. The
+    /// replaced error no longer appears in the chain or in the Display and
+    /// Debug output, but its value can still be reached by downcasting.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("permission denied").context("failed to open /home/alice/secret");
+    /// let error = error.map_top(|message| message.replace("/home/alice", "~"));
+    /// assert_eq!(error.to_string(), "failed to open ~/secret");
+    /// assert_eq!(error.root_cause().to_string(), "permission denied");
+    /// ```
+    pub fn map_top<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&str) -> String,
+    {
+        let mut message = String::new();
+        let _ = write!(message, "{}", self);
+        let message = f(&message);
+
+        // Extensions always live on the outermost error.
+        let extensions = self.inner.extensions.take();
+
+        // So does a backtrace from the backtrace crate, as it cannot be found
+        // through source() like a std one.
+        #[cfg(backtrace_crate)]
+        let backtrace = self.inner.backtrace.take();
+
+        let error: ContextError<String, ReplacedHead> = ContextError {
+            context: message,
+            error: ReplacedHead(self),
+            location: None,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<String, ReplacedHead>>,
+            object_ref: object_ref::<ContextError<String, ReplacedHead>>,
+            #[cfg(any(feature = "std", error_in_core))]
+            object_mut: object_mut::<ContextError<String, ReplacedHead>>,
+            object_boxed: object_boxed::<ContextError<String, ReplacedHead>>,
+            object_downcast: context_chain_downcast::<String>,
+            object_drop_rest: context_chain_drop_rest::<String>,
+            object_context: replaced_head_layer,
+            object_type_id: context_chain_type_id::<String>,
+            object_peel: no_peel,
+            object_layer_mut: no_layer_mut,
+        };
+
+        // As the replaced error is anyhow::Error, we already have a backtrace
+        // for it.
+        #[cfg(not(backtrace_crate))]
+        let backtrace = None;
+
+        // Safety: ReplacedHead is repr(transparent) so the vtable entries
+        // written for ContextError<String, Error> operate on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, extensions) }
+    }
+
+    /// Attach a value of type `T` to this error, returning the value of the
+    /// same type that was previously attached, if any.
+    ///
+    /// Extensions are carried along when context is added to the error, so a
+    /// value attached deep inside an application can be read back by the code
+    /// that eventually handles the error.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// struct RequestId(u64);
+    ///
+    /// fn handle() -> Result<()> {
+    ///     let mut error = anyhow!("upstream unavailable");
+    ///     error.insert_ext(RequestId(7));
+    ///     Err(error).context("failed to handle request")
+    /// }
+    ///
+    /// let error = handle().unwrap_err();
+    /// assert_eq!(error.get_ext::<RequestId>().unwrap().0, 7);
+    /// ```
+    pub fn insert_ext<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.inner.extensions.insert(value)
+    }
+
+    /// Get the value of type `T` attached to this error by
+    /// [`insert_ext`][Error::insert_ext].
+    pub fn get_ext<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.inner.extensions.get::<T>()
+    }
+
+    /// Get the backtrace for this Error.
+    ///
+    /// Backtraces are only available on the nightly channel. Tracking issue:
+    /// [rust-lang/rust#53487][tracking].
+    ///
+    /// In order for the backtrace to be meaningful, the environment variable
+    /// `RUST_LIB_BACKTRACE=1` must be defined. Backtraces are somewhat
+    /// expensive to capture in Rust, so we don't necessarily want to be
+    /// capturing them all over the place all the time.
+    ///
+    /// [tracking]: https://github.com/rust-lang/rust/issues/53487
+    #[cfg(backtrace)]
+    pub fn backtrace(&self) -> &Backtrace {
+        self.inner.backtrace()
+    }
+
+    /// The backtrace as it is printed at the end of the Debug representation,
+    /// starting with the `Stack backtrace:` line, or `None` if no backtrace
+    /// was captured.
+    ///
+    /// The header and the [compact layout][crate::FormatConfig::compact_backtrace]
+    /// follow the global [`FormatConfig`][crate::FormatConfig]. This is meant
+    /// for logging the backtrace in a field of its own, apart from the
+    /// message.
+    pub fn backtrace_string(&self) -> Option<String> {
+        self.inner.backtrace_string()
+    }
+
+    /// Replace the backtrace of this error, or remove it with `None`, for
+    /// example to get Debug output that does not change from one run to the
+    /// next in snapshot tests.
+    ///
+    /// This also replaces a backtrace provided by the underlying error and
+    /// one carried over by [`clone_snapshot`][Error::clone_snapshot].
+    #[cfg(backtrace)]
+    pub fn set_backtrace(&mut self, backtrace: Option<Backtrace>) {
+        self.inner.extensions.remove::<BacktraceSnapshot>();
+        self.inner.backtrace = Some(backtrace.unwrap_or_else(Backtrace::disabled));
+    }
+
+    /// Capture a backtrace at this point if the error does not already hold a
+    /// captured one.
+    ///
+    /// This captures regardless of `RUST_LIB_BACKTRACE`, so an application
+    /// can leave capture disabled for errors that are created and handled
+    /// internally and call this on the errors that escape to a top-level
+    /// handler, where a backtrace is worth its cost.
+    ///
+    /// Whether the backtrace is printed is decided separately, see
+    /// [`FormatConfig::show_backtrace`][crate::FormatConfig::show_backtrace].
+    #[cfg(backtrace)]
+    pub fn capture_backtrace(&mut self) {
+        use std::backtrace::BacktraceStatus;
+
+        if let BacktraceStatus::Captured = self.backtrace().status() {
+            return;
+        }
+        self.inner.backtrace = Some(Backtrace::force_capture());
+    }
+
+    /// Capture a backtrace at this point if the error does not already hold a
+    /// captured one.
+    ///
+    /// This captures regardless of `RUST_LIB_BACKTRACE`, so an application
+    /// can leave capture disabled for errors that are created and handled
+    /// internally and call this on the errors that escape to a top-level
+    /// handler, where a backtrace is worth its cost.
+    ///
+    /// Whether the backtrace is printed is decided separately, see
+    /// [`FormatConfig::show_backtrace`][crate::FormatConfig::show_backtrace].
+    #[cfg(backtrace_crate)]
+    pub fn capture_backtrace(&mut self) {
+        if self.inner.captured_backtrace().is_some() {
+            return;
+        }
+        self.inner.backtrace = Some(Backtrace::force_capture());
+    }
+
+    /// Like [`capture_backtrace`][Error::capture_backtrace], taking and
+    /// returning the error by value so that it can be chained, as done by
+    /// [`boundary!`][crate::boundary].
+    ///
+    /// On toolchains where anyhow does not capture backtraces, this returns
+    /// the error unchanged.
+    #[cfg_attr(not(any(backtrace, backtrace_crate)), allow(unused_mut))]
+    pub fn ensure_backtrace(mut self) -> Self {
+        #[cfg(any(backtrace, backtrace_crate))]
+        self.capture_backtrace();
+        self
+    }
+
+    /// Render the Debug representation of this error with ANSI colors,
+    /// whether or not colors are turned on with
+    /// [`set_color`][crate::set_color].
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("oh no!");
+    /// eprintln!("Error: {:?}", error.debug_colored());
+    /// ```
+    #[cfg(feature = "color")]
+    pub fn debug_colored(&self) -> impl Debug + '_ {
+        DebugWithColor {
+            error: self,
+            color: true,
+        }
+    }
+
+    /// Render the Debug representation of this error with the
+    /// [`RootCauseFirst`][crate::RootCauseFirst] layout, whatever formatter is
+    /// installed with [`set_formatter`][crate::set_formatter].
+    ///
+    /// The sections and backtrace of the error are included as usual.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// assert_eq!(
+    ///     format!("{:?}", error.root_cause_first()),
+    ///     "failed to fetch\n\nCaused by:\n    connection refused",
+    /// );
+    /// ```
+    pub fn root_cause_first(&self) -> impl Debug + '_ {
+        DebugWithFormatter {
+            error: self,
+            formatter: crate::fmt::RootCauseFirst,
+        }
+    }
+
+    /// Render the Debug representation of this error with the
+    /// [`RootCauseLast`][crate::RootCauseLast] layout, starting from the root
+    /// cause. This is the same as the `{:+?}` selector.
+    ///
+    /// The sections and backtrace of the error are included as usual.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// assert_eq!(
+    ///     format!("{:?}", error.root_cause_last()),
+    ///     "connection refused\n\nWhich caused:\n    failed to fetch",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn root_cause_last(&self) -> impl Debug + '_ {
+        DebugWithFormatter {
+            error: self,
+            formatter: crate::fmt::RootCauseLast,
+        }
+    }
+
+    /// The complete Debug representation of this error, with its causes and
+    /// backtrace, as an owned string, for example to send to an error
+    /// reporting service.
+    ///
+    /// This is the same as `format!("{:?}", error)`, including the use of any
+    /// formatter installed with [`set_formatter`][crate::set_formatter], except
+    /// that it never contains ANSI colors.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// assert_eq!(
+    ///     error.report(),
+    ///     "failed to fetch\n\nCaused by:\n    connection refused",
+    /// );
+    /// ```
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let debug = DebugWithColor {
+            error: self,
+            color: false,
+        };
+        let _ = write!(report, "{:?}", debug);
+        report
+    }
+
+    /// Write the Debug representation of this error to `w` as it is rendered,
+    /// without first building it in a `String`, for example into a ring
+    /// buffer or a network sink.
+    ///
+    /// The output is the same as that of `format!("{:?}", error)`. If a
+    /// [post-processor][crate::set_debug_post_processor] is installed, it
+    /// needs the whole output at once, so then the output is built up before
+    /// being written after all.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// let mut out = String::new();
+    /// error.write_debug(&mut out).unwrap();
+    /// assert_eq!(out, format!("{:?}", error));
+    /// ```
+    pub fn write_debug(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        write!(w, "{:?}", self)
+    }
+
+    /// Render this error as GitHub-flavored Markdown, for pasting into an
+    /// issue tracker.
+    ///
+    /// The message of this error is a heading, its causes a numbered list,
+    /// each section a paragraph under its header in bold, and the backtrace,
+    /// if there is one and the Debug representation would print it, a code
+    /// block. Characters in the messages that Markdown would take for
+    /// formatting are escaped with a backslash.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    /// let error = anyhow!("no such key `port`").context("failed to load config");
+    /// assert_eq!(
+    ///     error.to_markdown(),
+    ///     "### failed to load config\n\n1. no such key \\`port\\`\n",
+    /// );
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        self.inner.markdown()
+    }
+
+    /// Write the Debug representation of this error to a `termcolor` sink,
+    /// setting colors through the sink so that they interoperate with the rest
+    /// of the program's output.
+    ///
+    /// If the sink does not support colors, the bytes written are the same as
+    /// those of [`report`][Error::report].
+    ///
+    /// ```
+    /// # extern crate termcolor_crate as termcolor;
+    /// # use anyhow::anyhow;
+    /// use termcolor::{ColorChoice, StandardStream};
+    ///
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+    /// error.write_colored(&mut stderr)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "termcolor")]
+    pub fn write_colored(&self, w: &mut dyn termcolor::WriteColor) -> std::io::Result<()> {
+        let color = w.supports_color();
+        let mut rendered = String::new();
+        let debug = DebugWithColor { error: self, color };
+        let _ = write!(rendered, "{:?}", debug);
+        if color {
+            crate::color::write_color(w, &rendered)
+        } else {
+            w.write_all(rendered.as_bytes())
+        }
+    }
+
+    /// Render this error and all of its causes on a single line, joined by
+    /// `separator`.
+    ///
+    /// This is the same as the alternate Display representation `{:#}`, which
+    /// joins with `": "`, but with a separator of the caller's choosing.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// let message = error.display_with_separator(" -> ").to_string();
+    /// assert_eq!(message, "failed to fetch -> connection refused");
+    /// ```
+    pub fn display_with_separator<'a>(&'a self, separator: &'a str) -> impl Display + 'a {
+        JoinedDisplay {
+            error: self,
+            separator,
+        }
+    }
+
+    /// Render this error and all of its causes on a single line, each cause
+    /// in parentheses after its index in the chain, as in
+    /// `top (1: middle) (2: root)`.
+    ///
+    /// The index of a cause is its position in [`chain`][Error::chain], in
+    /// which this error itself is at index 0. The alternate Display
+    /// representation `{:#}` is the same without the indices.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// let message = error.display_indexed().to_string();
+    /// assert_eq!(message, "failed to fetch (1: connection refused)");
+    /// ```
+    pub fn display_indexed(&self) -> impl Display + '_ {
+        IndexedDisplay { error: self }
+    }
+
+    /// Borrow this error as a trait object, for passing it to APIs that take
+    /// a `&dyn Error`.
+    ///
+    /// This is what the `Deref` and `AsRef` impls of Error return, without
+    /// the type annotations needed to pick one of the `AsRef` impls.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::error::Error as StdError;
+    /// use std::io;
+    ///
+    /// fn describe(error: &(dyn StdError + 'static)) -> String {
+    ///     match error.source() {
+    ///         Some(source) => format!("{}: {}", error, source),
+    ///         None => error.to_string(),
+    ///     }
+    /// }
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .unwrap_err();
+    /// assert_eq!(describe(error.as_ref_dyn()), "failed to read config: not found");
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn as_ref_dyn(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self.inner.error()
+    }
+
+    /// An iterator of the chain of source errors contained by this Error.
+    ///
+    /// This iterator will visit every error in the cause chain of this error
+    /// object, beginning with the error that this error object was created
+    /// from.
+    ///
+    /// The first item is always this error itself, so the iterator is never
+    /// empty, and the last item is the [root cause][Error::root_cause]. The
+    /// iterator is double-ended: `error.chain().rev()` starts from the root
+    /// cause, in the order printed by `{:+?}`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn underlying_io_error_kind(error: &Error) -> Option<io::ErrorKind> {
+    ///     for cause in error.chain() {
+    ///         if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+    ///             return Some(io_error.kind());
+    ///         }
+    ///     }
+    ///     None
+    /// }
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn chain(&self) -> Chain<'_> {
+        self.inner.chain()
+    }
+
+    /// An iterator of the [chain][Error::chain] of source errors, where each
+    /// error comes with the `TypeId` of the type it can be downcast to, if
+    /// that is known.
+    ///
+    /// The type is known for every error held by anyhow: the error this
+    /// `Error` was created from, each layer of context, and the error that
+    /// context was added to. Causes further down, reached through the
+    /// `source()` of those errors, come with `None`. This lets a caller match
+    /// each layer against the types it knows about without trying every
+    /// downcast in turn.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::any::TypeId;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+    ///     .context("failed to read config")
+    ///     .unwrap_err();
+    ///
+    /// let types: Vec<_> = error.chain_typed().map(|(_, type_id)| type_id).collect();
+    /// assert_eq!(types[0], Some(TypeId::of::<&str>()));
+    /// assert_eq!(types[1], Some(TypeId::of::<io::Error>()));
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn chain_typed(&self) -> impl Iterator<Item = (&(dyn StdError + 'static), Option<TypeId>)> {
+        self.chain()
+            .map(move |error| (error, self.inner.type_id_of(error)))
+    }
+
+    /// Call `f` with each error of the [chain][Error::chain] of source
+    /// errors along with its depth, starting from this error itself at depth
+    /// 0, without collecting the chain first.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use std::fmt::Write;
+    ///
+    /// let error = anyhow!("root cause").context("outer");
+    /// let mut tree = String::new();
+    /// error.walk(|depth, error| {
+    ///     let _ = writeln!(tree, "{:1$}{2}", "", depth * 2, error);
+    /// });
+    /// assert_eq!(tree, "outer\n  root cause\n");
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn walk<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &(dyn StdError + 'static)),
+    {
+        for (depth, error) in self.chain().enumerate() {
+            f(depth, error);
+        }
+    }
+
+    /// The number of errors in the chain of source errors, including this
+    /// error itself.
+    ///
+    /// This is the same as `error.chain().len()` and walks the chain without
+    /// allocating.
+    pub fn chain_len(&self) -> usize {
+        self.inner.chain().len()
+    }
+
+    /// The error at position `n` of the [chain][Error::chain] of source
+    /// errors, where 0 is this error itself, or `None` if the chain is not that
+    /// long.
+    ///
+    /// This is the same as `error.chain().nth(n)`.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("root cause").context("outer");
+    /// assert_eq!(error.source_at(1).unwrap().to_string(), "root cause");
+    /// assert!(error.source_at(2).is_none());
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn source_at(&self, n: usize) -> Option<&(dyn StdError + 'static)> {
+        self.chain().nth(n)
+    }
+
+    /// Whether this error and `other` have the same messages, in the same
+    /// order, at every level of their [chain][Error::chain] of source errors.
+    ///
+    /// Only the Display representations are compared, so backtraces, types
+    /// and metadata may differ. This is meant for assertions in tests.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let a = anyhow!("not found").context("failed to load");
+    /// let b = anyhow!("not found").context("failed to load");
+    /// assert!(a.eq_message(&b));
+    /// assert!(!a.eq_message(&anyhow!("failed to load")));
+    /// ```
+    pub fn eq_message(&self, other: &Error) -> bool {
+        let mine = self.inner.chain();
+        let theirs = other.inner.chain();
+        if mine.len() != theirs.len() {
+            return false;
+        }
+        let mut a = String::new();
+        let mut b = String::new();
+        mine.zip(theirs).all(|(mine, theirs)| {
+            a.clear();
+            b.clear();
+            let _ = write!(a, "{}", mine);
+            let _ = write!(b, "{}", theirs);
+            a == b
+        })
+    }
+
+    /// The messages of this error and all of its sources, as owned strings,
+    /// for passing a failure across a thread or FFI boundary.
+    ///
+    /// The messages are in the order of [`chain`][Error::chain], outermost
+    /// first and root cause last, which is also the order of the default Debug
+    /// representation.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("connection refused").context("failed to fetch");
+    /// assert_eq!(error.chain_messages(), ["failed to fetch", "connection refused"]);
+    /// ```
+    pub fn chain_messages(&self) -> Vec<String> {
+        self.inner
+            .chain()
+            .map(|error| {
+                let mut message = String::new();
+                let _ = write!(message, "{}", error);
+                message
+            })
+            .collect()
+    }
+
+    /// The message of this error alone on a single line, for places with
+    /// room for little more than a few words, such as a status bar.
+    ///
+    /// The lines of the Display representation are trimmed and joined with
+    /// spaces, leaving out blank ones. Causes and the backtrace are not
+    /// included.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("failed to parse config:\n    unexpected `}`\n");
+    /// assert_eq!(error.short(), "failed to parse config: unexpected `}`");
+    /// ```
+    pub fn short(&self) -> String {
+        let mut message = String::new();
+        let _ = write!(message, "{}", self.inner.error());
+        let mut short = String::with_capacity(message.len());
+        for line in message
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            if !short.is_empty() {
+                short.push(' ');
+            }
+            short.push_str(line);
+        }
+        short
+    }
+
+    /// The messages of the context layers of this error, added by
+    /// [`context`][Error::context] or the [`Context`][crate::Context] trait,
+    /// from the outermost inward.
+    ///
+    /// The error that context was first added to is not included, so this is
+    /// the story of what the program was trying to do, without the low-level
+    /// error that stopped it. The result is empty if no context was added.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .context("failed to start server")
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     error.context_messages(),
+    ///     ["failed to start server", "failed to read config"],
+    /// );
+    /// ```
+    pub fn context_messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        let mut error = Some(self);
+        while let Some(layer) = error.and_then(|error| unsafe {
+            // Use vtable to find the context, if this error is a context
+            // error.
+            (error.inner.vtable.object_context)(&error.inner)
+        }) {
+            let mut message = String::new();
+            let _ = write!(message, "{}", layer.context);
+            messages.push(message);
+            error = layer.error;
+        }
+        messages
+    }
+
+    /// Remove the context layers from this error, returning the error that
+    /// context was first added to, or this error itself if it has no context.
+    ///
+    /// The backtrace and the extensions of this error are kept. An error whose
+    /// message was replaced by [`map_top`][Error::map_top] is not a context
+    /// layer, so removing layers stops there.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .context("failed to start server")
+    ///     .unwrap_err();
+    ///
+    /// let error = error.into_inner();
+    /// assert_eq!(error.to_string(), "not found");
+    /// assert!(error.is::<io::Error>());
+    /// ```
+    pub fn into_inner(self) -> Error {
+        let mut error = self;
+        loop {
+            // Read Box<ErrorImpl<()>> from the error, whose Drop impl must not
+            // run since the box is handed on.
+            let outer = ManuallyDrop::new(error);
+            let erased = ManuallyDrop::into_inner(unsafe { ptr::read(&outer.inner) });
+            // Use vtable to take apart the context layer, if it is one.
+            match unsafe { (erased.vtable.object_peel)(erased) } {
+                Ok(inner) => error = inner,
+                Err(error) => return error,
+            }
+        }
+    }
+
+    /// Wrap the error at the given depth of the [chain][Error::chain] with
+    /// additional context, so that the context appears at that depth and the
+    /// errors from there on move one level down.
+    ///
+    /// Depth 0 is this error itself, making this the same as
+    /// [`context`][Error::context]. Context can only be inserted above errors
+    /// held by anyhow, which are the errors that context was added to, not
+    /// the sources of an error reported by its own `source()` method, nor
+    /// the error whose message was replaced by [`map_top`][Error::map_top].
+    /// If the depth is past the last of them, the context is inserted right
+    /// above it.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .context("failed to start server")
+    ///     .unwrap_err();
+    ///
+    /// let error = error.insert_context_at(2, "config.toml");
+    /// let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    /// assert_eq!(
+    ///     messages,
+    ///     ["failed to start server", "failed to read config", "config.toml", "not found"],
+    /// );
+    /// ```
+    pub fn insert_context_at<C>(mut self, depth: usize, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        fn insert<C>(error: &mut Error, depth: usize, context: C)
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            if depth > 0 {
+                // Use vtable to find the error below this layer, if any.
+                if let Some(inner) = unsafe { (error.inner.vtable.object_layer_mut)(error) } {
+                    return insert(inner, depth - 1, context);
+                }
+            }
+            error.push_context(context);
+        }
+
+        insert(&mut self, depth, context);
+        self
+    }
+
+    /// The lowest level cause of this error &mdash; this error's cause's
+    /// cause's cause etc.
+    ///
+    /// The root cause is the last error in the iterator produced by
+    /// [`chain()`][Error::chain].
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        let mut chain = self.chain();
+        let mut root_cause = chain.next().unwrap();
+        for cause in chain {
+            root_cause = cause;
+        }
+        root_cause
+    }
+
+    /// Returns true if the [root cause][Error::root_cause] of this error is of
+    /// type `E`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    /// use std::io;
+    ///
+    /// fn read_config() -> Result<String> {
+    ///     fs::read_to_string("/nonexistent/config.toml").context("failed to read config")
+    /// }
+    ///
+    /// let error = read_config().unwrap_err();
+    /// assert!(error.root_cause_is::<io::Error>());
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn root_cause_is<E>(&self) -> bool
+    where
+        E: StdError + 'static,
+    {
+        self.root_cause().is::<E>()
+    }
+
+    /// Downcast to the first error of type `E` anywhere in the chain of source
+    /// errors.
+    ///
+    /// Unlike [`downcast_ref`][Error::downcast_ref], which only sees this error
+    /// object and any context attached to it, this also looks into the
+    /// `source()` of the underlying error, its source, and so on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use thiserror::Error;
+    /// use anyhow::{Context, Result};
+    /// use std::io;
+    ///
+    /// #[derive(Error, Debug)]
+    /// #[error("request failed")]
+    /// struct HttpError {
+    ///     #[source]
+    ///     io: io::Error,
+    /// }
+    ///
+    /// fn fetch() -> Result<()> {
+    ///     let io = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    ///     Err(HttpError { io }).context("failed to fetch resource")
+    /// }
+    ///
+    /// let error = fetch().unwrap_err();
+    /// assert!(error.downcast_ref::<io::Error>().is_none());
+    /// let io_error = error.downcast_chain_ref::<io::Error>().unwrap();
+    /// assert_eq!(io_error.kind(), io::ErrorKind::TimedOut);
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn downcast_chain_ref<E>(&self) -> Option<&E>
     where
-        C: Display + Send + Sync + 'static,
+        E: StdError + Send + Sync + 'static,
     {
-        let error: ContextError<C, Error> = ContextError {
-            context,
-            error: self,
-        };
+        self.downcast_ref::<E>()
+            .or_else(|| self.chain().find_map(|cause| cause.downcast_ref::<E>()))
+    }
 
-        let vtable = &ErrorVTable {
-            object_drop: object_drop::<ContextError<C, Error>>,
-            object_ref: object_ref::<ContextError<C, Error>>,
-            #[cfg(feature = "std")]
-            object_mut: object_mut::<ContextError<C, Error>>,
-            object_boxed: object_boxed::<ContextError<C, Error>>,
-            object_downcast: context_chain_downcast::<C>,
-            object_drop_rest: context_chain_drop_rest::<C>,
-        };
+    /// Downcast by mutable reference to the first error of type `E` among the
+    /// layers of this error, that is the underlying error and the context
+    /// attached to it, in the order of [`chain()`][Error::chain].
+    ///
+    /// Unlike [`downcast_chain_ref`][Error::downcast_chain_ref], this does not
+    /// look into the `source()` of the underlying error, since `source()`
+    /// only gives shared references.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let mut error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .context("failed to start server")
+    ///     .unwrap_err();
+    ///
+    /// *error.downcast_chain_mut::<io::Error>().unwrap() = io::ErrorKind::TimedOut.into();
+    /// assert_eq!(error.root_cause().to_string(), "timed out");
+    /// ```
+    pub fn downcast_chain_mut<E>(&mut self) -> Option<&mut E>
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        let mut error = self;
+        loop {
+            if error.is::<E>() {
+                return error.downcast_mut::<E>();
+            }
+            // Use vtable to find the error below this layer, if any.
+            let layer_mut = error.inner.vtable.object_layer_mut;
+            error = unsafe { layer_mut(error) }?;
+        }
+    }
 
-        // As the cause is anyhow::Error, we already have a backtrace for it.
-        let backtrace = None;
+    /// Returns true if any error in the chain of source errors, including this
+    /// error itself, is of type `E`.
+    ///
+    /// ```
+    /// # use anyhow::Result;
+    /// # use std::io;
+    /// #
+    /// fn status(error: &anyhow::Error) -> u16 {
+    ///     if error.chain_contains::<io::Error>() {
+    ///         return 503;
+    ///     }
+    ///     500
+    /// }
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn chain_contains<E>(&self) -> bool
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        self.downcast_chain_ref::<E>().is_some()
+    }
 
-        // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+    /// The first error in the chain of source errors for which `predicate`
+    /// returns true.
+    ///
+    /// The chain is visited in the same order as [`chain()`][Error::chain],
+    /// beginning with this error itself.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn find_cause<P>(&self, mut predicate: P) -> Option<&(dyn StdError + 'static)>
+    where
+        P: FnMut(&(dyn StdError + 'static)) -> bool,
+    {
+        self.chain().find(|cause| predicate(*cause))
     }
 
-    /// Get the backtrace for this Error.
+    /// Returns true if `predicate` returns true for any error in the chain of
+    /// source errors, including this error itself.
     ///
-    /// Backtraces are only available on the nightly channel. Tracking issue:
-    /// [rust-lang/rust#53487][tracking].
+    /// This is [`find_cause`][Error::find_cause] for when only whether there
+    /// is a match matters.
     ///
-    /// In order for the backtrace to be meaningful, the environment variable
-    /// `RUST_LIB_BACKTRACE=1` must be defined. Backtraces are somewhat
-    /// expensive to capture in Rust, so we don't necessarily want to be
-    /// capturing them all over the place all the time.
+    /// ```
+    /// # use anyhow::anyhow;
+    /// # use std::io;
+    /// #
+    /// fn is_transient(error: &anyhow::Error) -> bool {
+    ///     error.matches(|cause| match cause.downcast_ref::<io::Error>() {
+    ///         Some(io) => io.kind() == io::ErrorKind::TimedOut,
+    ///         None => cause.to_string().contains("try again"),
+    ///     })
+    /// }
     ///
-    /// [tracking]: https://github.com/rust-lang/rust/issues/53487
-    #[cfg(backtrace)]
-    pub fn backtrace(&self) -> &Backtrace {
-        self.inner.backtrace()
+    /// assert!(is_transient(&anyhow!("server busy, try again").context("upload failed")));
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn matches<P>(&self, predicate: P) -> bool
+    where
+        P: FnMut(&(dyn StdError + 'static)) -> bool,
+    {
+        self.find_cause(predicate).is_some()
     }
 
-    /// An iterator of the chain of source errors contained by this Error.
+    /// The first error in the chain of source errors, including this error
+    /// itself, for which `predicate` returns true.
     ///
-    /// This iterator will visit every error in the cause chain of this error
-    /// object, beginning with the error that this error object was created
-    /// from.
-    ///
-    /// # Example
+    /// This is the same as [`find_cause`][Error::find_cause], named to go
+    /// with [`matches`][Error::matches].
     ///
     /// ```
-    /// use anyhow::Error;
-    /// use std::io;
-    ///
-    /// pub fn underlying_io_error_kind(error: &Error) -> Option<io::ErrorKind> {
-    ///     for cause in error.chain() {
-    ///         if let Some(io_error) = cause.downcast_ref::<io::Error>() {
-    ///             return Some(io_error.kind());
-    ///         }
-    ///     }
-    ///     None
-    /// }
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("connection reset").context("failed to fetch");
+    /// let cause = error.find(|cause| cause.to_string().contains("reset"));
+    /// assert_eq!(cause.unwrap().to_string(), "connection reset");
     /// ```
-    #[cfg(feature = "std")]
-    pub fn chain(&self) -> Chain {
-        self.inner.chain()
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn find<P>(&self, predicate: P) -> Option<&(dyn StdError + 'static)>
+    where
+        P: FnMut(&(dyn StdError + 'static)) -> bool,
+    {
+        self.find_cause(predicate)
     }
 
-    /// The lowest level cause of this error &mdash; this error's cause's
-    /// cause's cause etc.
+    /// Returns true if the two errors are the same error object, in the
+    /// sense of [`Rc::ptr_eq`][std::rc::Rc::ptr_eq], rather than two errors
+    /// that happen to display the same.
     ///
-    /// The root cause is the last error in the iterator produced by
-    /// [`chain()`][Error::chain].
-    #[cfg(feature = "std")]
-    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
-        let mut chain = self.chain();
-        let mut root_cause = chain.next().unwrap();
-        for cause in chain {
-            root_cause = cause;
-        }
-        root_cause
+    /// A [snapshot][Error::clone_snapshot] is a new error, so it is never
+    /// pointer-equal to the error it was taken of.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("oh no!");
+    /// let same = &error;
+    /// assert!(error.ptr_eq(same));
+    /// assert!(!error.ptr_eq(&anyhow!("oh no!")));
+    /// ```
+    pub fn ptr_eq(&self, other: &Error) -> bool {
+        let this: &ErrorImpl<()> = &self.inner;
+        let other: &ErrorImpl<()> = &other.inner;
+        ptr::eq(this, other)
     }
 
     /// Returns true if `E` is the type held by this error object.
@@ -358,6 +1465,29 @@ impl Error {
     }
 
     /// Attempt to downcast the error object to a concrete type.
+    ///
+    /// If the error does not hold a value of type `E`, it is handed back
+    /// untouched in the `Err` variant, along with its context, backtrace and
+    /// extensions, so the caller can try another type or propagate it.
+    ///
+    /// ```
+    /// # use anyhow::{anyhow, Error};
+    /// # use std::io;
+    /// #
+    /// # fn handle(error: Error) -> Result<(), Error> {
+    /// let error = match error.downcast::<io::Error>() {
+    ///     Ok(io_error) => return Ok(eprintln!("I/O error: {}", io_error)),
+    ///     Err(error) => error,
+    /// };
+    /// let error = match error.downcast::<std::fmt::Error>() {
+    ///     Ok(_) => return Ok(eprintln!("formatting failed")),
+    ///     Err(error) => error,
+    /// };
+    /// Err(error)
+    /// # }
+    /// #
+    /// # assert!(handle(anyhow!("oh no!")).is_err());
+    /// ```
     pub fn downcast<E>(self) -> Result<E, Self>
     where
         E: Display + Debug + Send + Sync + 'static,
@@ -390,6 +1520,38 @@ impl Error {
         }
     }
 
+    /// Take the cause of type `T` out of this error by value, dropping the
+    /// rest of the error.
+    ///
+    /// The cause can be any layer that anyhow itself holds: the error that
+    /// context was first added to, or the value of any context. A cause that
+    /// is only reachable through the `source()` of another error is borrowed
+    /// from that error and cannot be moved out, so for such causes the error
+    /// is handed back unchanged in the `Err` variant, as it is when there is
+    /// no cause of type `T` at all. Use [`chain`][Error::chain] and
+    /// `downcast_ref` to inspect those.
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "not found"))
+    ///     .context("failed to read config")
+    ///     .context("failed to start")
+    ///     .unwrap_err();
+    ///
+    /// let io_error = error.take_cause::<io::Error>().unwrap();
+    /// assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    /// ```
+    pub fn take_cause<T>(self) -> Result<T, Self>
+    where
+        T: Display + Debug + Send + Sync + 'static,
+    {
+        // Every layer held by anyhow is reachable through the vtable, which is
+        // exactly what downcast searches.
+        self.downcast()
+    }
+
     /// Downcast this error object by reference.
     ///
     /// # Example
@@ -439,6 +1601,34 @@ impl Error {
         }
     }
 
+    /// Downcast this error object by reference, or return `default` if it is
+    /// not of type `E`.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("not an io::Error");
+    /// let fallback = std::io::Error::new(std::io::ErrorKind::Other, "unknown");
+    /// let io_error = error.downcast_ref_or(&fallback);
+    /// assert_eq!(io_error.kind(), std::io::ErrorKind::Other);
+    /// ```
+    pub fn downcast_ref_or<'a, E>(&'a self, default: &'a E) -> &'a E
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        self.downcast_ref().unwrap_or(default)
+    }
+
+    /// Downcast this error object by reference, or return the result of
+    /// calling `f` if it is not of type `E`.
+    pub fn downcast_ref_or_else<'a, E, F>(&'a self, f: F) -> &'a E
+    where
+        E: Display + Debug + Send + Sync + 'static,
+        F: FnOnce() -> &'a E,
+    {
+        self.downcast_ref().unwrap_or_else(f)
+    }
+
     /// Downcast this error object by mutable reference.
     pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
     where
@@ -454,7 +1644,7 @@ impl Error {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl<E> From<E> for Error
 where
     E: StdError + Send + Sync + 'static,
@@ -465,7 +1655,7 @@ where
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl Deref for Error {
     type Target = dyn StdError + Send + Sync + 'static;
 
@@ -474,7 +1664,7 @@ impl Deref for Error {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl DerefMut for Error {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.inner.error_mut()
@@ -493,6 +1683,54 @@ impl Debug for Error {
     }
 }
 
+struct DebugWithColor<'a> {
+    error: &'a Error,
+    color: bool,
+}
+
+impl Debug for DebugWithColor<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.error.inner.debug_with_color(formatter, self.color)
+    }
+}
+
+struct IndexedDisplay<'a> {
+    error: &'a Error,
+}
+
+impl Display for IndexedDisplay<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.error.inner.display_indexed(formatter)
+    }
+}
+
+struct DebugWithFormatter<'a, F> {
+    error: &'a Error,
+    formatter: F,
+}
+
+impl<F> Debug for DebugWithFormatter<'_, F>
+where
+    F: ErrorFormatter,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.error
+            .inner
+            .debug_with_formatter(formatter, &self.formatter)
+    }
+}
+
+struct JoinedDisplay<'a> {
+    error: &'a Error,
+    separator: &'a str,
+}
+
+impl Display for JoinedDisplay<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.error.inner.display_joined(formatter, self.separator)
+    }
+}
+
 impl Drop for Error {
     fn drop(&mut self) {
         unsafe {
@@ -509,11 +1747,24 @@ impl Drop for Error {
 struct ErrorVTable {
     object_drop: unsafe fn(Box<ErrorImpl<()>>),
     object_ref: unsafe fn(&ErrorImpl<()>) -> &(dyn StdError + Send + Sync + 'static),
-    #[cfg(feature = "std")]
+    #[cfg(any(feature = "std", error_in_core))]
     object_mut: unsafe fn(&mut ErrorImpl<()>) -> &mut (dyn StdError + Send + Sync + 'static),
     object_boxed: unsafe fn(Box<ErrorImpl<()>>) -> Box<dyn StdError + Send + Sync + 'static>,
     object_downcast: unsafe fn(&ErrorImpl<()>, TypeId) -> Option<NonNull<()>>,
     object_drop_rest: unsafe fn(Box<ErrorImpl<()>>, TypeId),
+    object_context: unsafe fn(&ErrorImpl<()>) -> Option<ContextLayer>,
+    #[cfg_attr(not(any(feature = "std", error_in_core)), allow(dead_code))]
+    object_type_id: unsafe fn(&ErrorImpl<()>, &(dyn StdError + 'static)) -> Option<TypeId>,
+    object_peel: unsafe fn(Box<ErrorImpl<()>>) -> Result<Error, Error>,
+    object_layer_mut: unsafe fn(&mut Error) -> Option<&mut Error>,
+}
+
+// The context of an error created by `context` or `with_context`, along with
+// the anyhow::Error it was added to, if it was added to one.
+struct ContextLayer<'a> {
+    context: &'a dyn Display,
+    error: Option<&'a Error>,
+    location: Option<&'static Location<'static>>,
 }
 
 // Safety: requires layout of *e to match ErrorImpl<E>.
@@ -544,7 +1795,7 @@ where
 }
 
 // Safety: requires layout of *e to match ErrorImpl<E>.
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 unsafe fn object_mut<E>(e: &mut ErrorImpl<()>) -> &mut (dyn StdError + Send + Sync + 'static)
 where
     E: StdError + Send + Sync + 'static,
@@ -578,8 +1829,221 @@ where
     }
 }
 
+// The vtable of an error of type E held directly, with no context.
+#[cfg(any(feature = "std", error_in_core))]
+fn std_vtable<E>() -> &'static ErrorVTable
+where
+    E: StdError + Send + Sync + 'static,
+{
+    &ErrorVTable {
+        object_drop: object_drop::<E>,
+        object_ref: object_ref::<E>,
+        #[cfg(any(feature = "std", error_in_core))]
+        object_mut: object_mut::<E>,
+        object_boxed: object_boxed::<E>,
+        object_downcast: object_downcast::<E>,
+        object_drop_rest: object_drop_front::<E>,
+        object_context: no_context,
+        object_type_id: object_type_id::<E>,
+        object_peel: no_peel,
+        object_layer_mut: no_layer_mut,
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E> or an ErrorImpl that
+// downcasts to E.
+unsafe fn object_type_id<E>(e: &ErrorImpl<()>, error: &(dyn StdError + 'static)) -> Option<TypeId>
+where
+    E: 'static,
+{
+    let object: &(dyn StdError + 'static) = e.error();
+    if ptr::eq(object, error) {
+        Some(TypeId::of::<E>())
+    } else {
+        None
+    }
+}
+
+// Not a context error, so no requirement on the layout of *e.
+unsafe fn no_context(_e: &ErrorImpl<()>) -> Option<ContextLayer<'_>> {
+    None
+}
+
+// Not a context error, so no requirement on the layout of *e.
+unsafe fn no_peel(e: Box<ErrorImpl<()>>) -> Result<Error, Error> {
+    Err(Error {
+        inner: ManuallyDrop::new(e),
+    })
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(any(feature = "std", error_in_core))]
+unsafe fn context_peel<C, E>(e: Box<ErrorImpl<()>>) -> Result<Error, Error>
+where
+    C: 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    let unerased = mem::transmute::<Box<ErrorImpl<()>>, Box<ErrorImpl<ContextError<C, E>>>>(e);
+    let ErrorImpl {
+        backtrace,
+        extensions,
+        _object: ContextError { error, .. },
+        ..
+    } = *unerased;
+    // The error was already seen by the hook when the context was added.
+    Ok(Error::assemble(
+        error,
+        std_vtable::<E>(),
+        backtrace,
+        extensions,
+    ))
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_peel<C>(e: Box<ErrorImpl<()>>) -> Result<Error, Error>
+where
+    C: 'static,
+{
+    let unerased = mem::transmute::<Box<ErrorImpl<()>>, Box<ErrorImpl<ContextError<C, Error>>>>(e);
+    let ErrorImpl {
+        backtrace,
+        extensions,
+        _object: ContextError { mut error, .. },
+        ..
+    } = *unerased;
+    // Put back what was moved to the outermost error when the context was
+    // added.
+    error.inner.extensions = extensions;
+    if backtrace.is_some() && error.inner.backtrace.is_none() {
+        error.inner.backtrace = backtrace;
+    }
+    Ok(error)
+}
+
+// Not a context error, so no requirement on the layout of the error.
+unsafe fn no_layer_mut(_error: &mut Error) -> Option<&mut Error> {
+    None
+}
+
+// Safety: requires layout of the error to match ErrorImpl<ContextError<C, E>>.
+#[cfg(any(feature = "std", error_in_core))]
+unsafe fn context_layer_mut<C, E>(error: &mut Error) -> Option<&mut Error>
+where
+    C: Display + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    // Turn the layer into context added to an anyhow::Error holding E, which
+    // can then be reached like the error below any other context.
+    error.replace_with(|error| {
+        let outer = ManuallyDrop::new(error);
+        let erased = ManuallyDrop::into_inner(ptr::read(&outer.inner));
+        let unerased =
+            mem::transmute::<Box<ErrorImpl<()>>, Box<ErrorImpl<ContextError<C, E>>>>(erased);
+        let ErrorImpl {
+            backtrace,
+            extensions,
+            _object:
+                ContextError {
+                    context,
+                    error,
+                    location,
+                },
+            ..
+        } = *unerased;
+        // Neither half is a new error, so the error hook is not run again.
+        let inner = Error::assemble(error, std_vtable::<E>(), backtrace, Extensions::new());
+        let mut error = inner.wrap_context(context, location);
+        error.inner.extensions = extensions;
+        error
+    });
+    (error.inner.vtable.object_layer_mut)(error)
+}
+
+// Safety: requires layout of the error to match
+// ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_layer_mut<C>(error: &mut Error) -> Option<&mut Error>
+where
+    C: 'static,
+{
+    let erased: &mut ErrorImpl<()> = &mut error.inner;
+    let unerased = erased as *mut ErrorImpl<()> as *mut ErrorImpl<ContextError<C, Error>>;
+    Some(&mut (*unerased)._object.error)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(any(feature = "std", error_in_core))]
+unsafe fn context_layer<C, E>(e: &ErrorImpl<()>) -> Option<ContextLayer<'_>>
+where
+    C: Display + 'static,
+{
+    let unerased = e as *const ErrorImpl<()> as *const ErrorImpl<ContextError<C, E>>;
+    Some(ContextLayer {
+        context: &(*unerased)._object.context,
+        error: None,
+        location: (*unerased)._object.location,
+    })
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_layer<C>(e: &ErrorImpl<()>) -> Option<ContextLayer<'_>>
+where
+    C: Display + 'static,
+{
+    let unerased = e as *const ErrorImpl<()> as *const ErrorImpl<ContextError<C, Error>>;
+    Some(ContextLayer {
+        context: &(*unerased)._object.context,
+        error: Some(&(*unerased)._object.error),
+        location: (*unerased)._object.location,
+    })
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<String, Error>>.
+unsafe fn replaced_head_layer(e: &ErrorImpl<()>) -> Option<ContextLayer<'_>> {
+    let unerased = e as *const ErrorImpl<()> as *const ErrorImpl<ContextError<String, Error>>;
+    let replaced = &(*unerased)._object.error;
+    // The replaced error is left out of the layers as it is of the chain, so
+    // the layer below it comes next, if it was a context layer.
+    let below = (replaced.inner.vtable.object_context)(&replaced.inner)?;
+    Some(ContextLayer {
+        context: &(*unerased)._object.context,
+        error: below.error,
+        location: None,
+    })
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(any(feature = "std", error_in_core))]
+unsafe fn context_type_id<C, E>(
+    e: &ErrorImpl<()>,
+    error: &(dyn StdError + 'static),
+) -> Option<TypeId>
+where
+    C: 'static,
+    E: StdError + 'static,
+{
+    let unerased = e as *const ErrorImpl<()> as *const ErrorImpl<ContextError<C, E>>;
+    let source: &(dyn StdError + 'static) = &(*unerased)._object.error;
+    if ptr::eq(source, error) {
+        Some(TypeId::of::<E>())
+    } else {
+        object_type_id::<C>(e, error)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_type_id<C>(
+    e: &ErrorImpl<()>,
+    error: &(dyn StdError + 'static),
+) -> Option<TypeId>
+where
+    C: 'static,
+{
+    // The errors below the context are in the inner anyhow::Error's layers.
+    object_type_id::<C>(e, error)
+}
+
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 unsafe fn context_downcast<C, E>(e: &ErrorImpl<()>, target: TypeId) -> Option<NonNull<()>>
 where
     C: 'static,
@@ -599,7 +2063,7 @@ where
 }
 
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 unsafe fn context_drop_rest<C, E>(e: Box<ErrorImpl<()>>, target: TypeId)
 where
     C: 'static,
@@ -672,6 +2136,10 @@ where
 pub(crate) struct ErrorImpl<E> {
     vtable: &'static ErrorVTable,
     backtrace: Option<Backtrace>,
+    pub(crate) extensions: Extensions,
+    // Where anyhow! or bail! created this error.
+    #[cfg(feature = "track-caller")]
+    creation_location: Option<&'static Location<'static>>,
     // NOTE: Don't use directly. Use only through vtable. Erased type may have
     // different alignment.
     _object: E,
@@ -683,8 +2151,15 @@ pub(crate) struct ErrorImpl<E> {
 pub(crate) struct ContextError<C, E> {
     pub context: C,
     pub error: E,
+    // Where the context was added, if it was added by context_at!.
+    pub location: Option<&'static Location<'static>>,
 }
 
+// The error whose message was replaced by Error::map_top. Its own message is
+// skipped by the source() of the ContextError holding it.
+#[repr(transparent)]
+pub(crate) struct ReplacedHead(pub Error);
+
 impl<E> ErrorImpl<E> {
     fn erase(&self) -> &ErrorImpl<()> {
         // Erase the concrete type of E but preserve the vtable in self.vtable
@@ -698,10 +2173,74 @@ impl ErrorImpl<()> {
     pub(crate) fn error(&self) -> &(dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
         // original type E.
-        unsafe { &*(self.vtable.object_ref)(self) }
+        unsafe { (self.vtable.object_ref)(self) }
     }
 
-    #[cfg(feature = "std")]
+    // The ErrorImpl, among this one and those nested in it as context, whose
+    // object is `error` in the chain.
+    fn layer_of(&self, error: &(dyn StdError + 'static)) -> Option<&ErrorImpl<()>> {
+        let mut inner = self;
+        loop {
+            let object: &(dyn StdError + 'static) = inner.error();
+            if ptr::eq(object, error) {
+                return Some(inner);
+            }
+            // Use vtable to find the context, if this error is a context
+            // error.
+            let layer = unsafe { (inner.vtable.object_context)(inner) }?;
+            inner = &layer.error?.inner;
+        }
+    }
+
+    // The type that `error` in the chain downcasts to, if it is held by this
+    // error or one nested in it as context.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn type_id_of(&self, error: &(dyn StdError + 'static)) -> Option<TypeId> {
+        let mut inner = self;
+        loop {
+            if let Some(type_id) = unsafe { (inner.vtable.object_type_id)(inner, error) } {
+                return Some(type_id);
+            }
+            let layer = unsafe { (inner.vtable.object_context)(inner) }?;
+            inner = &layer.error?.inner;
+        }
+    }
+
+    // Where the context layer that is `error` in the chain was added, if it is
+    // a context layer with a recorded location.
+    pub(crate) fn context_location(
+        &self,
+        error: &(dyn StdError + 'static),
+    ) -> Option<&'static Location<'static>> {
+        let inner = self.layer_of(error)?;
+        let layer = unsafe { (inner.vtable.object_context)(inner) }?;
+        layer.location
+    }
+
+    // Where the error that is `error` in the chain was created by anyhow! or
+    // bail!, if the track-caller feature is enabled.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn creation_location(
+        &self,
+        error: &(dyn StdError + 'static),
+    ) -> Option<&'static Location<'static>> {
+        #[cfg(feature = "track-caller")]
+        {
+            let location = self
+                .layer_of(error)
+                .and_then(|inner| inner.creation_location);
+            #[cfg(any(feature = "std", error_in_core))]
+            let location = location.or_else(|| crate::snapshot::creation_location(error));
+            location
+        }
+        #[cfg(not(feature = "track-caller"))]
+        {
+            let _ = error;
+            None
+        }
+    }
+
+    #[cfg(any(feature = "std", error_in_core))]
     pub(crate) fn error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
         // original type E.
@@ -719,7 +2258,16 @@ impl ErrorImpl<()> {
             .expect("backtrace capture failed")
     }
 
-    pub(crate) fn chain(&self) -> Chain {
+    // The backtrace from the backtrace crate, which lives on the outermost
+    // error, if one was captured.
+    #[cfg(backtrace_crate)]
+    pub(crate) fn captured_backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace
+            .as_ref()
+            .filter(|backtrace| backtrace.is_captured())
+    }
+
+    pub(crate) fn chain(&self) -> Chain<'_> {
         Chain::new(self.error())
     }
 }
@@ -736,6 +2284,11 @@ where
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         self.erase().error().source()
     }
+
+    #[cfg(provide)]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        self.erase().error().provide(request);
+    }
 }
 
 impl<E> Debug for ErrorImpl<E>
@@ -778,14 +2331,14 @@ impl From<Error> for Box<dyn StdError + 'static> {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl AsRef<dyn StdError + Send + Sync> for Error {
     fn as_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
         &**self
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl AsRef<dyn StdError> for Error {
     fn as_ref(&self) -> &(dyn StdError + 'static) {
         &**self
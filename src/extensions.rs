@@ -0,0 +1,74 @@
+use crate::alloc::{Box, Vec};
+use core::any::Any;
+
+// Typed values attached to an error, at most one per type. Kept to a single
+// word so that errors without extensions pay only for a null pointer.
+pub(crate) struct Extensions {
+    #[allow(clippy::box_collection)]
+    map: Option<Box<Vec<Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    pub(crate) fn new() -> Self {
+        Extensions { map: None }
+    }
+
+    pub(crate) fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let map = self.map.get_or_insert_with(Box::default);
+        let previous = match map.iter().position(|ext| ext.is::<T>()) {
+            Some(i) => downcast(map.swap_remove(i)),
+            None => None,
+        };
+        map.push(Box::new(value));
+        previous
+    }
+
+    pub(crate) fn get<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.map
+            .as_ref()?
+            .iter()
+            .find_map(|ext| ext.downcast_ref::<T>())
+    }
+
+    pub(crate) fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: 'static,
+    {
+        self.map
+            .as_mut()?
+            .iter_mut()
+            .find_map(|ext| ext.downcast_mut::<T>())
+    }
+
+    #[cfg_attr(not(backtrace), allow(dead_code))]
+    pub(crate) fn remove<T>(&mut self) -> Option<T>
+    where
+        T: 'static,
+    {
+        let map = self.map.as_mut()?;
+        let i = map.iter().position(|ext| ext.is::<T>())?;
+        downcast(map.swap_remove(i))
+    }
+
+    pub(crate) fn take(&mut self) -> Self {
+        Extensions {
+            map: self.map.take(),
+        }
+    }
+}
+
+// Box<dyn Any + Send + Sync> has no downcast of its own on the minimum
+// supported Rust version, unlike Box<dyn Any>.
+fn downcast<T>(ext: Box<dyn Any + Send + Sync>) -> Option<T>
+where
+    T: 'static,
+{
+    let ext: Box<dyn Any> = ext;
+    ext.downcast::<T>().ok().map(|ext| *ext)
+}
@@ -0,0 +1,58 @@
+use crate::alloc::String;
+use crate::Error;
+use core::fmt::{Display, Write};
+
+/// Receiver of the fields of an error recorded by
+/// [`Error::record_fields`], for example an adapter that records them on a
+/// `tracing` span or into a structured log event.
+pub trait FieldVisitor {
+    /// Record the field called `name`.
+    fn record(&mut self, name: &str, value: &dyn Display);
+}
+
+impl Error {
+    /// Record this error as separate fields rather than as one message:
+    ///
+    /// - `error.message`, the message of this error itself,
+    /// - `error.chain.0`, `error.chain.1` and so on, one for each cause, from
+    ///   the outermost to the root cause, numbered as in the Debug
+    ///   representation,
+    /// - `error.backtrace`, if a backtrace was captured, as returned by
+    ///   [`backtrace_string`][Error::backtrace_string].
+    ///
+    /// ```
+    /// use anyhow::{anyhow, FieldVisitor};
+    /// use std::fmt::Display;
+    ///
+    /// struct Fields(Vec<(String, String)>);
+    ///
+    /// impl FieldVisitor for Fields {
+    ///     fn record(&mut self, name: &str, value: &dyn Display) {
+    ///         self.0.push((name.to_owned(), value.to_string()));
+    ///     }
+    /// }
+    ///
+    /// let error = anyhow!("connection reset").context("failed to fetch");
+    /// let mut fields = Fields(Vec::new());
+    /// error.record_fields(&mut fields);
+    /// assert_eq!(fields.0[0], ("error.message".to_owned(), "failed to fetch".to_owned()));
+    /// assert_eq!(fields.0[1], ("error.chain.0".to_owned(), "connection reset".to_owned()));
+    /// ```
+    pub fn record_fields(&self, visitor: &mut dyn FieldVisitor) {
+        let mut chain = self.inner.chain();
+        if let Some(error) = chain.next() {
+            visitor.record("error.message", &error);
+        }
+
+        let mut name = String::new();
+        for (n, cause) in chain.enumerate() {
+            name.clear();
+            let _ = write!(name, "error.chain.{}", n);
+            visitor.record(&name, &cause);
+        }
+
+        if let Some(backtrace) = self.backtrace_string() {
+            visitor.record("error.backtrace", &backtrace);
+        }
+    }
+}
@@ -1,57 +1,1672 @@
+#[cfg(any(feature = "std", error_in_core))]
+use crate::aggregate::Aggregate;
+use crate::alloc::{Cow, String, Vec};
 use crate::chain::Chain;
+use crate::color::{Hyperlink, Style};
 use crate::error::ErrorImpl;
-use core::fmt::{self, Debug};
+use crate::extensions::Extensions;
+#[cfg(feature = "thread-origin")]
+use crate::metadata::ThreadOrigin;
+use crate::metadata::{Code, HelpUrls, Sections, Severity};
+use crate::StdError;
+use core::fmt::{self, Debug, Display, Write};
+use core::panic::Location;
 
-impl ErrorImpl<()> {
-    pub(crate) fn display(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.error())?;
+#[cfg(any(feature = "std", error_in_core))]
+use crate::snapshot::BacktraceSnapshot;
+#[cfg(backtrace)]
+use std::backtrace::Backtrace;
 
-        if f.alternate() {
-            for cause in self.chain().skip(1) {
-                write!(f, ": {}", cause)?;
+#[cfg(feature = "std")]
+use crate::lazy::Lazy;
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, RwLock};
+
+/// The pieces of an error that are handed to an [`ErrorFormatter`].
+///
+/// An `ErrorInfo` is constructed by anyhow each time an `Error` is formatted
+/// with `{:?}` and is borrowed from the error for the duration of the call.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub struct ErrorInfo<'a> {
+    inner: &'a ErrorImpl<()>,
+    error: &'a (dyn StdError + 'static),
+    extensions: &'a Extensions,
+    #[cfg(backtrace)]
+    backtrace: &'a Backtrace,
+    color: bool,
+    config: &'a FormatConfig,
+}
+
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+impl<'a> ErrorInfo<'a> {
+    /// The outermost error, whose `source()` chain leads to the root cause.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn error(&self) -> &'a (dyn StdError + 'static) {
+        self.error
+    }
+
+    /// The value of type `T` attached to the error by
+    /// [`Error::insert_ext`][crate::Error::insert_ext], for formatters that
+    /// want to render extensions.
+    pub fn get_ext<T>(&self) -> Option<&'a T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions.get::<T>()
+    }
+
+    /// Where the context that is `error` in the chain was added, if it was
+    /// added with [`context_at!`][crate::context_at].
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn location(&self, error: &(dyn StdError + 'static)) -> Option<&'static Location<'static>> {
+        self.inner.context_location(error)
+    }
+
+    /// The sections attached with
+    /// [`Error::with_section`][crate::Error::with_section], as pairs of header
+    /// and body, in the order they were attached.
+    pub fn sections(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.get_ext::<Sections>()
+            .into_iter()
+            .flat_map(|sections| &sections.0)
+            .map(|section| (section.header.as_str(), section.body.as_str()))
+    }
+
+    /// The URLs attached with
+    /// [`Error::with_help_url`][crate::Error::with_help_url], in the order
+    /// they were attached.
+    pub fn help_urls(&self) -> impl Iterator<Item = &'a str> {
+        self.get_ext::<HelpUrls>()
+            .into_iter()
+            .flat_map(|urls| &urls.0)
+            .map(String::as_str)
+    }
+
+    /// Where `error` in the chain was created by [`anyhow!`][crate::anyhow]
+    /// or [`bail!`][crate::bail]. Always `None` unless the `track-caller`
+    /// feature is enabled.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub fn creation_location(
+        &self,
+        error: &(dyn StdError + 'static),
+    ) -> Option<&'static Location<'static>> {
+        self.inner.creation_location(error)
+    }
+
+    /// The backtrace captured for this error.
+    #[cfg(backtrace)]
+    pub fn backtrace(&self) -> &'a Backtrace {
+        self.backtrace
+    }
+
+    /// Whether the output should be decorated with ANSI colors.
+    #[cfg(feature = "color")]
+    pub fn color(&self) -> bool {
+        self.color
+    }
+}
+
+/// Custom rendering for the Debug representation of `Error`.
+///
+/// The default formatter is [`RootCauseFirst`], which produces the "Caused
+/// by:" layout shown in the documentation of [`Error`][crate::Error], unless
+/// the `ANYHOW_FORMAT` environment variable picks another built-in one, see
+/// [`formatter_from_env`]. Install a different one with [`set_formatter`].
+///
+/// The alternate selector "{:#?}" is not routed through the formatter and
+/// always produces the conventional struct-style Debug representation.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{ErrorFormatter, ErrorInfo};
+/// use std::fmt;
+///
+/// struct Tree;
+///
+/// impl ErrorFormatter for Tree {
+///     fn fmt_error(&self, info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "{}", info.error())?;
+///         let mut source = info.error().source();
+///         while let Some(cause) = source {
+///             write!(f, "\n└─ {}", cause)?;
+///             source = cause.source();
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// anyhow::set_formatter(Box::new(Tree));
+/// ```
+pub trait ErrorFormatter: Send + Sync + 'static {
+    /// Write the Debug representation of the error described by `info`.
+    fn fmt_error(&self, info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// Layout options for the built-in formatters, [`RootCauseFirst`] and
+/// [`RootCauseLast`].
+///
+/// Install options for the whole process with [`set_format_config`].
+///
+/// ```
+/// use anyhow::FormatConfig;
+///
+/// anyhow::set_format_config(FormatConfig::new().indent(2));
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub struct FormatConfig {
+    indent: usize,
+    align_continuation: bool,
+    wrap: Option<usize>,
+    dedup: bool,
+    dedup_all: bool,
+    max_causes: Option<usize>,
+    truncate: Option<usize>,
+    caused_by: Cow<'static, str>,
+    which_caused: Cow<'static, str>,
+    #[cfg_attr(not(any(backtrace, backtrace_crate)), allow(dead_code))]
+    backtrace_header: Cow<'static, str>,
+    #[cfg_attr(not(any(backtrace, backtrace_crate)), allow(dead_code))]
+    compact_backtrace: bool,
+    show_backtrace: Option<bool>,
+    blank_lines: bool,
+    gutter: GutterStyle,
+    number_threshold: usize,
+    #[cfg(feature = "thread-origin")]
+    show_thread: bool,
+    #[cfg(feature = "track-caller")]
+    show_creation_location: bool,
+}
+
+/// How each of several causes is marked in the Debug representation, set by
+/// [`FormatConfig::gutter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub enum GutterStyle {
+    /// The index of the cause, as in `0: `.
+    Numbered,
+    /// A bullet, as in `• `.
+    Bullet,
+    /// An arrow, as in `↳ `.
+    Arrow,
+}
+
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+impl FormatConfig {
+    /// The default options, which render errors as shown in the
+    /// documentation of [`Error`][crate::Error].
+    pub const fn new() -> Self {
+        FormatConfig {
+            indent: 4,
+            align_continuation: false,
+            wrap: None,
+            dedup: false,
+            dedup_all: false,
+            max_causes: None,
+            truncate: None,
+            caused_by: Cow::Borrowed("Caused by:"),
+            which_caused: Cow::Borrowed("Which caused:"),
+            backtrace_header: Cow::Borrowed("Stack backtrace:"),
+            compact_backtrace: false,
+            show_backtrace: None,
+            blank_lines: true,
+            gutter: GutterStyle::Numbered,
+            number_threshold: 2,
+            #[cfg(feature = "thread-origin")]
+            show_thread: false,
+            #[cfg(feature = "track-caller")]
+            show_creation_location: true,
+        }
+    }
+
+    /// Number of spaces in front of each cause. The default is 4.
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = width;
+        self
+    }
+
+    /// Indent the lines after the first one in a multi-line cause to line up
+    /// with the text after the cause's number, rather than leaving them at
+    /// the start of the line. Off by default.
+    pub fn align_continuation(mut self, yes: bool) -> Self {
+        self.align_continuation = yes;
+        self
+    }
+
+    /// Break causes into lines of at most `columns` characters, counting the
+    /// indentation, by wrapping at spaces. Wrapped lines, and the other lines
+    /// of a multi-line cause, are indented to line up with the text after the
+    /// cause's number. Words longer than the available width are left whole.
+    /// Off by default.
+    pub fn wrap(mut self, columns: usize) -> Self {
+        self.wrap = Some(columns);
+        self
+    }
+
+    /// Like [`wrap`][FormatConfig::wrap] with the width of the terminal, as
+    /// reported by the `COLUMNS` environment variable, or 80 if it is not set.
+    #[cfg(feature = "std")]
+    pub fn wrap_to_terminal(self) -> Self {
+        let columns = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .filter(|&columns| columns > 0)
+            .unwrap_or(80);
+        self.wrap(columns)
+    }
+
+    /// Collapse adjacent errors of the chain that display identically into a
+    /// single entry with a count, as in `failed to connect (x2)`. Errors that
+    /// are equal but not adjacent are all kept. Off by default.
+    pub fn dedup(mut self, yes: bool) -> Self {
+        self.dedup = yes;
+        self
+    }
+
+    /// Leave out every error of the chain that displays identically to one
+    /// printed before it, wherever it is, keeping the first occurrence and the
+    /// order of the rest. This is for chains that repeat a generic message at
+    /// several levels. Unlike [`dedup`][FormatConfig::dedup] no count is
+    /// shown, and when both are on, the repeats that directly follow the first
+    /// occurrence are still counted. Off by default.
+    pub fn dedup_all(mut self, yes: bool) -> Self {
+        self.dedup_all = yes;
+        self
+    }
+
+    /// Print at most `limit` causes, followed by a line saying how many more
+    /// were left out, as in `... (95 more causes)`. This guards against
+    /// pathologically deep chains. Unlimited by default.
+    pub fn max_causes(mut self, limit: usize) -> Self {
+        self.max_causes = Some(limit);
+        self
+    }
+
+    /// Cut the message of each error of the chain down to its first `limit`
+    /// characters, followed by `…`, for errors that embed large payloads
+    /// such as a whole response body. Sections and the backtrace are left
+    /// whole. Unlimited by default.
+    pub fn truncate(mut self, limit: usize) -> Self {
+        self.truncate = Some(limit);
+        self
+    }
+
+    /// The line introducing the causes in the [`RootCauseFirst`] layout. The
+    /// default is `Caused by:`.
+    pub fn caused_by_header(mut self, header: impl Into<Cow<'static, str>>) -> Self {
+        self.caused_by = header.into();
+        self
+    }
+
+    /// The line introducing the errors caused by the root cause in the
+    /// [`RootCauseLast`] layout. The default is `Which caused:`.
+    pub fn which_caused_header(mut self, header: impl Into<Cow<'static, str>>) -> Self {
+        self.which_caused = header.into();
+        self
+    }
+
+    /// The line introducing the backtrace, in place of the standard library's
+    /// `stack backtrace:`. The default is `Stack backtrace:`.
+    pub fn backtrace_header(mut self, header: impl Into<Cow<'static, str>>) -> Self {
+        self.backtrace_header = header.into();
+        self
+    }
+
+    /// Print the backtrace with one line per frame, as in
+    /// `3: app::main at src/main.rs:12`, leaving out addresses and columns and
+    /// making paths relative to the current directory. See
+    /// [`compact_backtrace`][crate::compact_backtrace]. Off by default, which
+    /// prints the backtrace as the standard library renders it.
+    pub fn compact_backtrace(mut self, yes: bool) -> Self {
+        self.compact_backtrace = yes;
+        self
+    }
+
+    /// Whether to print the backtrace of an error that holds one, whatever
+    /// the environment says.
+    ///
+    /// By default this is decided by the same environment variables that
+    /// decide whether backtraces are captured: the backtrace is left out if
+    /// `RUST_LIB_BACKTRACE` is `0`, or if it is unset and `RUST_BACKTRACE` is
+    /// `0`. The variables are read once, the first time it matters. This
+    /// applies to backtraces captured regardless of those variables too, such
+    /// as by `Error::capture_backtrace`, so that they can be captured for
+    /// telemetry and only printed where wanted. The backtrace is still
+    /// available from
+    /// [`Error::backtrace_string`][crate::Error::backtrace_string].
+    pub fn show_backtrace(mut self, yes: bool) -> Self {
+        self.show_backtrace = Some(yes);
+        self
+    }
+
+    /// Decide whether to print backtraces from the given values of
+    /// `RUST_LIB_BACKTRACE` and `RUST_BACKTRACE` rather than from the
+    /// environment of the process, with the same precedence, for example to
+    /// follow variables that a program reads from its own configuration.
+    ///
+    /// This is the same as [`show_backtrace`][FormatConfig::show_backtrace]
+    /// with the answer the environment would give, and like it is overridden
+    /// by a later call to either.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, FormatConfig};
+    ///
+    /// // RUST_LIB_BACKTRACE takes precedence over RUST_BACKTRACE.
+    /// let config = FormatConfig::new().backtrace_env(Some("0"), Some("1"));
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// anyhow::set_format_config(config);
+    ///
+    /// let error = anyhow!("oh no!");
+    /// assert_eq!(format!("{:?}", error), "oh no!");
+    /// ```
+    pub fn backtrace_env(
+        mut self,
+        rust_lib_backtrace: Option<&str>,
+        rust_backtrace: Option<&str>,
+    ) -> Self {
+        self.show_backtrace = Some(shows_backtrace(rust_lib_backtrace, rust_backtrace));
+        self
+    }
+
+    /// Separate the first line, the list of causes, each section and the
+    /// backtrace by a blank line. On by default; turning it off gives denser
+    /// output, as in:
+    ///
+    /// ```console
+    /// Failed to read config
+    /// Caused by:
+    ///     No such file or directory (os error 2)
+    /// ```
+    pub fn blank_lines(mut self, yes: bool) -> Self {
+        self.blank_lines = yes;
+        self
+    }
+
+    /// How to mark each cause when there is more than one. With
+    /// [`align_continuation`][FormatConfig::align_continuation], lines after
+    /// the first one of a cause are indented to line up with the text after
+    /// the marker. The default is [`GutterStyle::Numbered`].
+    pub fn gutter(mut self, style: GutterStyle) -> Self {
+        self.gutter = style;
+        self
+    }
+
+    /// Mark the causes only when there are at least `count` of them. The
+    /// default is 2, so a lone cause is printed without a marker. With 1, a
+    /// lone cause is marked too, so that the first line is the only one
+    /// without a marker, as in:
+    ///
+    /// ```console
+    /// Failed to start server
+    ///
+    /// Caused by:
+    ///     0: No such file or directory (os error 2)
+    /// ```
+    pub fn number_threshold(mut self, count: usize) -> Self {
+        self.number_threshold = count;
+        self
+    }
+
+    /// Print the [thread the error was created on][crate::Error::origin_thread]
+    /// at the end of the first line, as in `disk full (thread: worker-2)`.
+    /// Off by default.
+    #[cfg(feature = "thread-origin")]
+    pub fn show_thread(mut self, yes: bool) -> Self {
+        self.show_thread = yes;
+        self
+    }
+
+    /// Show where each error of the chain was created by
+    /// [`anyhow!`][crate::anyhow] or [`bail!`][crate::bail] after its
+    /// message, as in `missing attribute (at src/parse.rs:10)`. On by
+    /// default, since enabling the `track-caller` feature asks for it.
+    #[cfg(feature = "track-caller")]
+    pub fn show_creation_location(mut self, yes: bool) -> Self {
+        self.show_creation_location = yes;
+        self
+    }
+}
+
+/// Shorten the Display representation of a `std::backtrace::Backtrace` as
+/// [`FormatConfig::compact_backtrace`] does.
+///
+/// Each frame is joined with the location on the line after it, paths under
+/// `root` or in the sources of the standard library are made relative, and
+/// addresses and column numbers are dropped. Lines that are not recognized
+/// are kept as they are.
+///
+/// ```
+/// use std::path::Path;
+///
+/// let backtrace = "\
+/// stack backtrace:
+///    0: app::main
+///              at /home/alice/app/src/main.rs:12:5
+///    1: core::ops::function::FnOnce::call_once
+///              at /rustc/90b35a623/library/core/src/ops/function.rs:250:5";
+///
+/// let expected = "\
+/// stack backtrace:
+///    0: app::main at src/main.rs:12
+///    1: core::ops::function::FnOnce::call_once at library/core/src/ops/function.rs:250";
+///
+/// let compact = anyhow::compact_backtrace(backtrace, Path::new("/home/alice/app"));
+/// assert_eq!(compact, expected);
+/// ```
+#[cfg(feature = "std")]
+pub fn compact_backtrace(backtrace: &str, root: &Path) -> String {
+    let root = root.to_str().unwrap_or("").trim_end_matches('/');
+    let mut compact = String::new();
+    // Whether the last line written is a function still waiting for its
+    // location.
+    let mut frame = false;
+    for line in backtrace.lines() {
+        let trimmed = line.trim_start();
+        match strip_prefix(trimmed, "at ") {
+            Some(location) if frame => {
+                let _ = write!(compact, " at {}", compact_location(location, root));
+                frame = false;
+                continue;
             }
+            _ => {}
+        }
+        if !compact.is_empty() {
+            compact.push('\n');
         }
+        frame = match split_frame(trimmed) {
+            Some((index, function)) => {
+                let indent = &line[..line.len() - trimmed.len()];
+                let _ = write!(compact, "{}{}: {}", indent, index, strip_address(function));
+                true
+            }
+            None => {
+                compact.push_str(line);
+                // Another function inlined into the frame above.
+                line.starts_with(' ')
+            }
+        };
+    }
+    compact
+}
+
+// The index and function of a line like `  12: app::main`.
+#[cfg(feature = "std")]
+fn split_frame(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(": ")?;
+    let index = &line[..colon];
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((index, &line[colon + 2..]))
+}
+
+// `app::main` out of `0x55d5c1e2 - app::main`, or `<unknown>` if there is only
+// an address.
+#[cfg(feature = "std")]
+fn strip_address(function: &str) -> &str {
+    let rest = match strip_prefix(function, "0x") {
+        Some(rest) => rest.trim_start_matches(|c: char| c.is_ascii_hexdigit()),
+        None => return function,
+    };
+    match strip_prefix(rest.trim_start(), "- ") {
+        Some(function) => function,
+        None if rest.trim().is_empty() => "<unknown>",
+        None => rest.trim_start(),
+    }
+}
+
+// `line` without `prefix`, if it starts with it.
+#[cfg(feature = "std")]
+pub(crate) fn strip_prefix<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    if line.starts_with(prefix) {
+        Some(&line[prefix.len()..])
+    } else {
+        None
+    }
+}
 
+// `src/main.rs:12` out of `/home/alice/app/src/main.rs:12:5` with root
+// `/home/alice/app`.
+#[cfg(feature = "std")]
+fn compact_location(location: &str, root: &str) -> String {
+    let mut parts = location.rsplitn(3, ':');
+    let (path, line) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(column), Some(line), Some(path))
+            if column.parse::<u32>().is_ok() && line.parse::<u32>().is_ok() =>
+        {
+            (path, line)
+        }
+        _ => return location.to_owned(),
+    };
+    let path = match strip_prefix(path, root).and_then(|path| strip_prefix(path, "/")) {
+        Some(relative) if !root.is_empty() => relative,
+        _ => match strip_prefix(path, "/rustc/") {
+            // Skip the commit hash.
+            Some(rustc) => rustc.find('/').map_or(path, |slash| &rustc[slash + 1..]),
+            None => path,
+        },
+    };
+    let mut compact = String::new();
+    let _ = write!(compact, "{}:{}", path, line);
+    compact
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig::new()
+    }
+}
+
+/// The default formatter: the outermost error on the first line, followed by
+/// its causes from the outermost to the root cause.
+pub struct RootCauseFirst;
+
+impl ErrorFormatter for RootCauseFirst {
+    fn fmt_error(&self, info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = &info.config.caused_by;
+        let chain = Chain::new(info.error);
+        let cyclic = chain.is_cyclic();
+        fmt_chain(info, chain, cyclic, header, f)
+    }
+}
+
+/// A formatter that starts from the root cause and works outward, so that the
+/// outermost context is printed last.
+///
+/// This formatter is selected by the sign flag, as in `format!("{:+?}", err)`.
+///
+/// ```console
+/// No such file or directory (os error 2)
+///
+/// Which caused:
+///     0: Failed to read config
+///     1: Failed to start server
+/// ```
+#[cfg(feature = "std")]
+pub struct RootCauseLast;
+
+#[cfg(feature = "std")]
+impl ErrorFormatter for RootCauseLast {
+    fn fmt_error(&self, info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = &info.config.which_caused;
+        let chain = Chain::new(info.error());
+        let cyclic = chain.is_cyclic();
+        fmt_chain(info, chain.rev(), cyclic, header, f)
+    }
+}
+
+// The first error of `chain` on the first line, then the others numbered
+// under `header`, then the backtrace. A cyclic chain stops short of the error
+// that source() leads back to, so the output says that more would have
+// followed.
+fn fmt_chain<'a, I>(
+    info: &ErrorInfo,
+    chain: I,
+    cyclic: bool,
+    header: &str,
+    f: &mut fmt::Formatter,
+) -> fmt::Result
+where
+    I: ExactSizeIterator<Item = &'a (dyn StdError + 'static)>,
+{
+    if info.config.dedup || info.config.dedup_all || info.config.truncate.is_some() {
+        let mut entries: Vec<Repeated<String>> = Vec::new();
+        for error in chain {
+            let mut message = String::new();
+            write!(message, "{}", error)?;
+            let seen =
+                info.config.dedup_all && entries.iter().any(|entry| entry.message == message);
+            let last = entries
+                .last_mut()
+                .filter(|last| info.config.dedup && last.message == message);
+            if let Some(last) = last {
+                last.count += 1;
+            } else if !seen {
+                entries.push(Repeated {
+                    message,
+                    count: 1,
+                    location: info.inner.context_location(error),
+                    creation_location: shown_creation_location(info, error),
+                });
+            }
+        }
+        if let Some(limit) = info.config.truncate {
+            for entry in &mut entries {
+                truncate(&mut entry.message, limit);
+            }
+        }
+        fmt_entries(info, entries.into_iter(), cyclic, header, f)
+    } else {
+        let entries = chain.map(|error| Repeated {
+            message: error,
+            count: 1,
+            location: info.inner.context_location(error),
+            creation_location: shown_creation_location(info, error),
+        });
+        fmt_entries(info, entries, cyclic, header, f)
+    }
+}
+
+fn fmt_entries<M, I>(
+    info: &ErrorInfo,
+    mut entries: I,
+    cyclic: bool,
+    header: &str,
+    f: &mut fmt::Formatter,
+) -> fmt::Result
+where
+    M: Display,
+    I: ExactSizeIterator<Item = Repeated<M>>,
+{
+    if let Some(headline) = entries.next() {
+        fmt_headline(info, headline, f)?;
+    }
+
+    if entries.len() > 0 || cyclic {
+        fmt_separator(info, f)?;
+        write!(f, "{}", header)?;
+        fmt_causes(info, entries, f)?;
+    }
+
+    if cyclic {
+        write!(f, "\n{:1$}(cycle detected)", "", info.config.indent)?;
+    }
+
+    fmt_members(info, f)?;
+    fmt_sections(info, f)?;
+    fmt_help_urls(info, f)?;
+    fmt_backtrace(info, f)
+}
+
+// The line break ending one part of the output before the next, followed by
+// a blank line unless the config asks for compact output.
+fn fmt_separator(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    if info.config.blank_lines {
+        f.write_str("\n\n")
+    } else {
+        f.write_str("\n")
+    }
+}
+
+// The members of an aggregate error in the chain, each numbered like a cause
+// and holding its own Debug representation.
+#[cfg(any(feature = "std", error_in_core))]
+fn fmt_members(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    let members = match Chain::new(info.error()).find_map(|error| error.downcast_ref::<Aggregate>())
+    {
+        Some(aggregate) if !aggregate.0.is_empty() => &aggregate.0,
+        _ => return Ok(()),
+    };
+    fmt_separator(info, f)?;
+    f.write_str("Errors:")?;
+    for (n, member) in members.iter().enumerate() {
+        writeln!(f)?;
+        let mut indented = Indented {
+            inner: f,
+            number: Some(n),
+            gutter: info.config.gutter,
+            indent: info.config.indent,
+            color: info.color,
+            align: true,
+            started: false,
+            pending_indent: false,
+        };
+        let member = Member {
+            error: member,
+            color: info.color,
+        };
+        write!(indented, "{:?}", member)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "std", error_in_core)))]
+fn fmt_members(_info: &ErrorInfo, _f: &mut fmt::Formatter) -> fmt::Result {
+    Ok(())
+}
+
+// A member of an aggregate, formatted in the same colors as the aggregate.
+#[cfg(any(feature = "std", error_in_core))]
+struct Member<'a> {
+    error: &'a crate::Error,
+    color: bool,
+}
+
+#[cfg(any(feature = "std", error_in_core))]
+impl Debug for Member<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.inner.debug_unprocessed(f, self.color)
+    }
+}
+
+// Each section attached by with_section as its header, followed by its body
+// indented like a cause.
+fn fmt_sections(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    for (header, body) in info.sections() {
+        fmt_separator(info, f)?;
+        writeln!(f, "{}", header)?;
+        let mut indented = Indented {
+            inner: f,
+            number: None,
+            gutter: info.config.gutter,
+            indent: info.config.indent,
+            color: info.color,
+            align: true,
+            started: false,
+            pending_indent: false,
+        };
+        indented.write_str(body)?;
+    }
+    Ok(())
+}
+
+// The URLs attached by with_help_url, one `See:` line each.
+fn fmt_help_urls(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    for (i, url) in info.help_urls().enumerate() {
+        if i == 0 {
+            fmt_separator(info, f)?;
+        } else {
+            writeln!(f)?;
+        }
+        let link = Hyperlink {
+            url,
+            text: url,
+            color: info.color,
+        };
+        write!(f, "See: {}", link)?;
+    }
+    Ok(())
+}
+
+// Where `error` was created, if that was recorded and is to be shown.
+fn shown_creation_location(
+    info: &ErrorInfo,
+    error: &(dyn StdError + 'static),
+) -> Option<&'static Location<'static>> {
+    #[cfg(feature = "track-caller")]
+    {
+        if info.config.show_creation_location {
+            return info.creation_location(error);
+        }
+    }
+    let _ = (info, error);
+    None
+}
+
+// A message standing for `count` identical adjacent errors of the chain, and
+// where the first of them was added as context or created, if that was
+// recorded.
+struct Repeated<M> {
+    message: M,
+    count: usize,
+    location: Option<&'static Location<'static>>,
+    creation_location: Option<&'static Location<'static>>,
+}
+
+impl<M> Display for Repeated<M>
+where
+    M: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(location) = self.location {
+            write!(f, " at {}:{}", location.file(), location.line())?;
+        }
+        if let Some(location) = self.creation_location {
+            write!(f, " (at {}:{})", location.file(), location.line())?;
+        }
+        if self.count > 1 {
+            write!(f, " (x{})", self.count)?;
+        }
         Ok(())
     }
+}
 
-    pub(crate) fn debug(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let error = self.error();
+// The list of causes below the first line, numbered if there is more than one.
+fn fmt_causes<M, I>(info: &ErrorInfo, causes: I, f: &mut fmt::Formatter) -> fmt::Result
+where
+    M: Display,
+    I: ExactSizeIterator<Item = M>,
+{
+    let total = causes.len();
+    let shown = info
+        .config
+        .max_causes
+        .map_or(total, |limit| total.min(limit));
+    let multiple = total >= info.config.number_threshold;
+    for (n, error) in causes.take(shown).enumerate() {
+        writeln!(f)?;
+        let mut indented = Indented {
+            inner: f,
+            number: if multiple { Some(n) } else { None },
+            gutter: info.config.gutter,
+            indent: info.config.indent,
+            color: info.color,
+            align: info.config.align_continuation || info.config.wrap.is_some(),
+            started: false,
+            pending_indent: false,
+        };
+        match info.config.wrap {
+            Some(columns) => {
+                let width = columns.saturating_sub(indented.gutter_width()).max(1);
+                let mut text = String::new();
+                write!(text, "{}", error)?;
+                indented.write_str(&wrap(&text, width))?;
+            }
+            None => write!(indented, "{}", error)?,
+        }
+    }
+    if shown < total {
+        let more = total - shown;
+        let plural = if more == 1 { "" } else { "s" };
+        write!(
+            f,
+            "\n{:indent$}... ({} more cause{})",
+            "",
+            more,
+            plural,
+            indent = info.config.indent,
+        )?;
+    }
+    Ok(())
+}
 
-        if f.alternate() {
-            return Debug::fmt(error, f);
+// Keep the first `limit` characters of `message`, marking that some were cut.
+fn truncate(message: &mut String, limit: usize) {
+    if let Some((end, _)) = message.char_indices().nth(limit) {
+        message.truncate(end);
+        message.push('…');
+    }
+}
+
+// Insert line breaks between words so that no line is wider than `width`,
+// keeping the line breaks already present in the text.
+fn wrap(text: &str, width: usize) -> String {
+    let mut wrapped = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            wrapped.push('\n');
+        }
+        let mut column = 0;
+        for (j, word) in line.split(' ').enumerate() {
+            let word_width = display_width(word);
+            if j > 0 {
+                if column > 0 && column + 1 + word_width > width {
+                    wrapped.push('\n');
+                    column = 0;
+                } else {
+                    wrapped.push(' ');
+                    column += 1;
+                }
+            }
+            wrapped.push_str(word);
+            column += word_width;
+        }
+    }
+    wrapped
+}
+
+// Number of characters in `word`, not counting ANSI escape sequences, which
+// take up no space on the terminal. Escapes never contain a space, so they
+// are never split across lines.
+fn display_width(word: &str) -> usize {
+    let mut width = 0;
+    let mut chars = word.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            if chars.next() == Some('[') {
+                for ch in chars.by_ref() {
+                    if ch >= '\x40' && ch <= '\x7e' {
+                        break;
+                    }
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+// The first line of output, decorated with the error's metadata.
+fn fmt_headline(info: &ErrorInfo, message: impl Display, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(severity) = info.get_ext::<Severity>() {
+        write!(f, "[{}] ", severity)?;
+    }
+    write!(f, "{}", Style::Bold.paint(message, info.color))?;
+    if let Some(code) = info.get_ext::<Code>() {
+        write!(f, " (code {})", code.0)?;
+    }
+    #[cfg(feature = "thread-origin")]
+    {
+        if info.config.show_thread {
+            if let Some(origin) = info.get_ext::<ThreadOrigin>() {
+                write!(f, " (thread: {})", origin.0)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fmt_backtrace(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(backtrace) = formatted_backtrace(info) {
+        if !show_backtrace(info.config) {
+            return Ok(());
+        }
+        let (header, frames) = match backtrace.find('\n') {
+            Some(newline) => backtrace.split_at(newline),
+            None => (backtrace.as_str(), ""),
+        };
+        fmt_separator(info, f)?;
+        write!(f, "{}", Style::Header.paint(header, info.color))?;
+        fmt_frames(frames, info.color, f)?;
+    }
+    Ok(())
+}
+
+// Whether a backtrace is printed: as configured, or else unless the
+// environment turns backtraces off.
+fn show_backtrace(config: &FormatConfig) -> bool {
+    if let Some(show) = config.show_backtrace {
+        return show;
+    }
+    #[cfg(feature = "std")]
+    let show = env_shows_backtrace();
+    #[cfg(not(feature = "std"))]
+    let show = true;
+    show
+}
+
+// RUST_LIB_BACKTRACE takes precedence over RUST_BACKTRACE, as it does for
+// capturing backtraces. Either turns them off only when it is `0`.
+fn shows_backtrace(rust_lib_backtrace: Option<&str>, rust_backtrace: Option<&str>) -> bool {
+    match rust_lib_backtrace.or(rust_backtrace) {
+        Some(value) => value != "0",
+        None => true,
+    }
+}
+
+#[cfg(feature = "std")]
+const UNDETECTED: u8 = 0;
+#[cfg(feature = "std")]
+const HIDDEN: u8 = 1;
+#[cfg(feature = "std")]
+const SHOWN: u8 = 2;
+
+// The environment is read the first time a backtrace is printed, not on every
+// format.
+#[cfg(feature = "std")]
+static ENV_BACKTRACE: AtomicU8 = AtomicU8::new(UNDETECTED);
+
+#[cfg(feature = "std")]
+fn env_shows_backtrace() -> bool {
+    match ENV_BACKTRACE.load(Ordering::Relaxed) {
+        UNDETECTED => {
+            let rust_lib_backtrace = std::env::var_os("RUST_LIB_BACKTRACE");
+            let rust_backtrace = std::env::var_os("RUST_BACKTRACE");
+            // A value that is not unicode is not `0` either.
+            let show = shows_backtrace(
+                rust_lib_backtrace
+                    .as_ref()
+                    .map(|value| value.to_str().unwrap_or("")),
+                rust_backtrace
+                    .as_ref()
+                    .map(|value| value.to_str().unwrap_or("")),
+            );
+            ENV_BACKTRACE.store(if show { SHOWN } else { HIDDEN }, Ordering::Relaxed);
+            show
         }
+        state => state == SHOWN,
+    }
+}
 
+// The frames of a backtrace, with the path of each source location made a
+// hyperlink to the file when colors are on.
+#[cfg(feature = "std")]
+fn fmt_frames(frames: &str, color: bool, f: &mut fmt::Formatter) -> fmt::Result {
+    if !color {
+        return f.write_str(frames);
+    }
+    for (i, line) in frames.split('\n').enumerate() {
+        if i > 0 {
+            f.write_char('\n')?;
+        }
+        match split_location(line) {
+            Some((before, path, after)) => {
+                let url = file_url(path);
+                let link = Hyperlink {
+                    url: &url,
+                    text: path,
+                    color,
+                };
+                write!(f, "{}{}{}", before, link, after)?;
+            }
+            None => f.write_str(line)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "std"))]
+fn fmt_frames(frames: &str, _color: bool, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(frames)
+}
+
+// A line holding a source location, such as `at src/main.rs:12:5` or the
+// compact `0: app::main at src/main.rs:12`, split into the text before the
+// path, the path, and the line and column after it.
+#[cfg(feature = "std")]
+fn split_location(line: &str) -> Option<(&str, &str, &str)> {
+    let trimmed = line.trim_start();
+    let start = if trimmed.starts_with("at ") {
+        line.len() - trimmed.len() + 3
+    } else {
+        line.rfind(" at ")? + 4
+    };
+    let location = &line[start..];
+    let mut path = location;
+    for _ in 0..2 {
+        let colon = match path.rfind(':') {
+            Some(colon) => colon,
+            None => break,
+        };
+        let number = &path[colon + 1..];
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        path = &path[..colon];
+    }
+    if path.is_empty() || path.len() == location.len() {
+        return None;
+    }
+    Some((&line[..start], path, &location[path.len()..]))
+}
+
+// A `file://` URL for `path`, which is taken to be relative to the current
+// directory unless it is absolute.
+#[cfg(feature = "std")]
+fn file_url(path: &str) -> String {
+    let path = Path::new(path);
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let absolute = absolute.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !absolute.starts_with('/') {
+        url.push('/');
+    }
+    for byte in absolute.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                url.push(byte as char);
+            }
+            _ => {
+                let _ = write!(url, "%{:02X}", byte);
+            }
+        }
+    }
+    url
+}
+
+// The backtrace as it is printed, under the configured header.
+fn formatted_backtrace(info: &ErrorInfo) -> Option<String> {
+    let mut backtrace = rendered_backtrace(info)?;
+    let std_header = "stack backtrace:";
+    if backtrace.starts_with(std_header) {
+        // Capitalized by default to match "Caused by:"
+        backtrace.replace_range(..std_header.len(), &info.config.backtrace_header);
+    }
+    backtrace.truncate(backtrace.trim_end().len());
+    #[cfg(feature = "std")]
+    {
+        if info.config.compact_backtrace {
+            let root = std::env::current_dir().unwrap_or_default();
+            backtrace = compact_backtrace(&backtrace, &root);
+        }
+    }
+    Some(backtrace)
+}
+
+// The captured backtrace as the standard library renders it, or the one
+// carried over from the error a snapshot was taken of.
+#[cfg_attr(not(any(feature = "std", error_in_core)), allow(unused_variables))]
+fn rendered_backtrace(info: &ErrorInfo) -> Option<String> {
+    #[cfg(backtrace)]
+    {
+        use std::backtrace::BacktraceStatus;
+
+        let backtrace = info.backtrace();
+        if let BacktraceStatus::Captured = backtrace.status() {
+            return Some(backtrace.to_string());
+        }
+    }
+
+    #[cfg(backtrace_crate)]
+    {
+        if let Some(backtrace) = info.inner.captured_backtrace() {
+            return Some(backtrace.to_string());
+        }
+    }
+
+    #[cfg(any(feature = "std", error_in_core))]
+    return info
+        .get_ext::<BacktraceSnapshot>()
+        .map(|snapshot| snapshot.0.clone());
+
+    #[cfg(not(any(feature = "std", error_in_core)))]
+    None
+}
+
+/// A `fmt::Write` adapter that lays out text like a cause in the Debug
+/// representation of an `Error`, for reports of errors of other kinds that
+/// should look the same.
+///
+/// The first line written through it is preceded by a gutter of spaces, 4 by
+/// default, and for a [numbered][Indented::numbered] writer by a marker such
+/// as `2: `. Every following line is indented to line up with the text after
+/// the marker, except empty lines, which are left empty.
+///
+/// The width of the gutter is counted in characters: a number takes one
+/// column per digit followed by `": "`, and the other [`GutterStyle`]s one
+/// column for the symbol followed by a space. Tabs and characters wider than
+/// one column in the written text are not accounted for, and the text is not
+/// wrapped.
+///
+/// ```
+/// use anyhow::Indented;
+/// use std::fmt::Write;
+///
+/// let mut report = String::new();
+/// for (n, line) in ["first\nsecond", "third"].iter().enumerate() {
+///     report.push('\n');
+///     Indented::numbered(&mut report, n).write_str(line).unwrap();
+/// }
+/// assert_eq!(report, "\n    0: first\n       second\n    1: third");
+/// ```
+pub struct Indented<'a, D: ?Sized> {
+    inner: &'a mut D,
+    number: Option<usize>,
+    gutter: GutterStyle,
+    indent: usize,
+    color: bool,
+    // Whether lines after the first are indented at all.
+    align: bool,
+    started: bool,
+    // A line was ended and the next one is not indented yet, which is put off
+    // until it turns out not to be empty.
+    pending_indent: bool,
+}
+
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+impl<'a, D> Indented<'a, D>
+where
+    D: Write + ?Sized,
+{
+    /// A writer with a gutter of spaces only.
+    pub fn new(inner: &'a mut D) -> Self {
+        Indented {
+            inner,
+            number: None,
+            gutter: GutterStyle::Numbered,
+            indent: 4,
+            color: false,
+            align: true,
+            started: false,
+            pending_indent: false,
+        }
+    }
+
+    /// A writer marking the first line with `number`, or with a symbol if a
+    /// different [`gutter`][Indented::gutter] style is set.
+    pub fn numbered(inner: &'a mut D, number: usize) -> Self {
+        Indented {
+            number: Some(number),
+            ..Indented::new(inner)
+        }
+    }
+
+    /// Number of spaces in front of the marker. The default is 4.
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = width;
+        self
+    }
+
+    /// How to mark the first line of a numbered writer. The default is
+    /// [`GutterStyle::Numbered`].
+    pub fn gutter(mut self, style: GutterStyle) -> Self {
+        self.gutter = style;
+        self
+    }
+
+    fn gutter_width(&self) -> usize {
+        match (self.number, self.gutter) {
+            (Some(mut number), GutterStyle::Numbered) => {
+                let mut digits = 1;
+                while number >= 10 {
+                    number /= 10;
+                    digits += 1;
+                }
+                self.indent + digits + ": ".len()
+            }
+            // A symbol one column wide and a space.
+            (Some(_), GutterStyle::Bullet) | (Some(_), GutterStyle::Arrow) => self.indent + 2,
+            (None, _) => self.indent,
+        }
+    }
+}
+
+impl<D> Write for Indented<'_, D>
+where
+    D: Write + ?Sized,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if !self.started {
+                self.started = true;
+                write!(self.inner, "{:1$}", "", self.indent)?;
+                if let Some(number) = self.number {
+                    match self.gutter {
+                        GutterStyle::Numbered => {
+                            write!(
+                                self.inner,
+                                "{} ",
+                                Style::Dimmed.paint(format_args!("{}:", number), self.color),
+                            )?;
+                        }
+                        GutterStyle::Bullet => {
+                            write!(self.inner, "{} ", Style::Dimmed.paint('•', self.color))?;
+                        }
+                        GutterStyle::Arrow => {
+                            write!(self.inner, "{} ", Style::Dimmed.paint('↳', self.color))?;
+                        }
+                    }
+                }
+            } else if i > 0 {
+                self.inner.write_char('\n')?;
+                self.pending_indent = self.align;
+            }
+            if !line.is_empty() {
+                if self.pending_indent {
+                    self.pending_indent = false;
+                    write!(self.inner, "{:1$}", "", self.gutter_width())?;
+                }
+                self.inner.write_str(line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The errors of `chain` on a single line, separated by `separator`.
+fn fmt_joined<'a, I>(chain: I, f: &mut fmt::Formatter, separator: &str) -> fmt::Result
+where
+    I: Iterator<Item = &'a (dyn StdError + 'static)>,
+{
+    for (n, error) in chain.enumerate() {
+        if n > 0 {
+            f.write_str(separator)?;
+        }
         write!(f, "{}", error)?;
+    }
+    Ok(())
+}
+
+// The chain on one line with each cause in parentheses after its index, as
+// in `top (1: middle) (2: root)`.
+fn fmt_indexed<'a, I>(chain: I, f: &mut fmt::Formatter) -> fmt::Result
+where
+    I: Iterator<Item = &'a (dyn StdError + 'static)>,
+{
+    for (n, error) in chain.enumerate() {
+        if n == 0 {
+            write!(f, "{}", error)?;
+        } else {
+            write!(f, " ({}: {})", n, error)?;
+        }
+    }
+    Ok(())
+}
 
-        if let Some(cause) = error.source() {
-            write!(f, "\n\nCaused by:")?;
-            let multiple = cause.source().is_some();
-            for (n, error) in Chain::new(cause).enumerate() {
-                write!(f, "\n    ")?;
-                if multiple {
-                    write!(f, "{}: ", n)?;
+// The error as Markdown: the first line of the outermost error as a heading,
+// the causes as a numbered list, each section as a paragraph under its header
+// in bold, and the backtrace in a code block.
+fn markdown(info: &ErrorInfo) -> String {
+    let mut markdown = String::new();
+    let mut message = String::new();
+    for (n, error) in Chain::new(info.error).enumerate() {
+        message.clear();
+        let _ = write!(message, "{}", error);
+        if n == 0 {
+            markdown.push_str("###");
+            for line in message.lines().filter(|line| !line.trim().is_empty()) {
+                markdown.push(' ');
+                escape_markdown(&mut markdown, line.trim());
+            }
+            markdown.push('\n');
+            continue;
+        }
+        if n == 1 {
+            markdown.push('\n');
+        }
+        let start = markdown.len();
+        let _ = write!(markdown, "{}. ", n);
+        let width = markdown.len() - start;
+        for (i, line) in message.lines().enumerate() {
+            if i > 0 {
+                markdown.push('\n');
+                if !line.is_empty() {
+                    let _ = write!(markdown, "{:1$}", "", width);
                 }
-                write!(f, "{}", error)?;
             }
+            escape_markdown(&mut markdown, line);
         }
+        markdown.push('\n');
+    }
+    for (header, body) in info.sections() {
+        markdown.push_str("\n**");
+        escape_markdown(&mut markdown, header);
+        markdown.push_str("**\n\n");
+        for line in body.lines() {
+            escape_markdown(&mut markdown, line);
+            markdown.push('\n');
+        }
+    }
+    let backtrace = formatted_backtrace(info).filter(|_| show_backtrace(info.config));
+    if let Some(backtrace) = backtrace {
+        // A fence longer than any run of backticks in the backtrace.
+        let mut fence = String::from("```");
+        while backtrace.contains(fence.as_str()) {
+            fence.push('`');
+        }
+        let _ = write!(markdown, "\n{}text\n{}\n{}\n", fence, backtrace, fence);
+    }
+    markdown
+}
 
-        #[cfg(backtrace)]
-        {
-            use std::backtrace::BacktraceStatus;
-
-            let backtrace = self.backtrace();
-            if let BacktraceStatus::Captured = backtrace.status() {
-                let mut backtrace = backtrace.to_string();
-                if backtrace.starts_with("stack backtrace:") {
-                    // Capitalize to match "Caused by:"
-                    backtrace.replace_range(0..1, "S");
+// Append `text` with a backslash before each character that Markdown could
+// take for formatting.
+fn escape_markdown(markdown: &mut String, text: &str) {
+    for ch in text.chars() {
+        if "\\`*_[]<>#|~".contains(ch) {
+            markdown.push('\\');
+        }
+        markdown.push(ch);
+    }
+}
+
+/// The errors remaining in the chain joined by `": "`, the same as the
+/// alternate Display representation `{:#}` of the error the chain came from.
+#[cfg(any(feature = "std", error_in_core))]
+impl Display for crate::Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_joined(self.clone(), f, ": ")
+    }
+}
+
+#[cfg(feature = "std")]
+static CONFIG: Lazy<RwLock<FormatConfig>> = lazy!(RwLock::new(FormatConfig::new()));
+
+/// Replace the layout options used by the built-in formatters for every
+/// `Error` in the process.
+///
+/// See [`FormatConfig`] for the available options.
+#[cfg(feature = "std")]
+pub fn set_format_config(config: FormatConfig) {
+    let mut global = CONFIG
+        .get()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    *global = config;
+}
+
+#[cfg(feature = "std")]
+static FORMATTER: Lazy<RwLock<Option<Arc<dyn ErrorFormatter>>>> = lazy!(RwLock::new(None));
+
+/// Replace the formatter used for the Debug representation of every `Error`
+/// in the process.
+///
+/// See [`ErrorFormatter`] for an example.
+#[cfg(feature = "std")]
+pub fn set_formatter(formatter: Box<dyn ErrorFormatter>) {
+    let mut global = FORMATTER
+        .get()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    *global = Some(Arc::from(formatter));
+}
+
+#[cfg(feature = "std")]
+static ENV_FORMATTER: Lazy<Option<Arc<dyn ErrorFormatter>>> =
+    lazy!(formatter_from_env(|name| std::env::var(name).ok()).map(Arc::from));
+
+/// The built-in formatter named by the `ANYHOW_FORMAT` variable of an
+/// environment, as looked up by `var`: [`RootCauseFirst`] for `root-first`
+/// and [`RootCauseLast`] for `root-last`. Any other value, or none, names no
+/// formatter.
+///
+/// The default formatter is picked this way from the environment of the
+/// process, read once when the first error is formatted, so the layout of
+/// errors can be switched without rebuilding. A formatter installed with
+/// [`set_formatter`] takes precedence. An application reading its settings
+/// from elsewhere can install the formatter they name itself:
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let settings = HashMap::from([("ANYHOW_FORMAT", "root-last")]);
+/// let formatter = anyhow::formatter_from_env(|name| settings.get(name).map(|v| v.to_string()));
+/// if let Some(formatter) = formatter {
+///     anyhow::set_formatter(formatter);
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn formatter_from_env<F>(var: F) -> Option<Box<dyn ErrorFormatter>>
+where
+    F: FnOnce(&str) -> Option<String>,
+{
+    match var("ANYHOW_FORMAT")?.as_str() {
+        "root-first" => Some(Box::new(RootCauseFirst)),
+        "root-last" => Some(Box::new(RootCauseLast)),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "std")]
+type PostProcessor = dyn Fn(&mut String) + Send + Sync;
+
+#[cfg(feature = "std")]
+static POST_PROCESSOR: Lazy<RwLock<Option<Arc<PostProcessor>>>> = lazy!(RwLock::new(None));
+
+/// Register a function to edit the Debug representation of every `Error` in
+/// the process after it is rendered and before it is written out, for
+/// example to redact secrets wherever they appear in error messages.
+///
+/// The function runs on the complete output, including the causes, sections
+/// and backtrace, whichever formatter rendered it, and on the alternate
+/// representation `{:#?}` too. Registering a function replaces the previous
+/// one.
+///
+/// ```
+/// use anyhow::anyhow;
+/// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+/// # #[cfg(feature = "track-caller")]
+/// # let config = config.show_creation_location(false);
+/// # anyhow::set_format_config(config);
+///
+/// anyhow::set_debug_post_processor(Box::new(|output| {
+///     *output = output.replace("hunter2", "******");
+/// }));
+///
+/// let error = anyhow!("login failed with password hunter2");
+/// assert_eq!(format!("{:?}", error), "login failed with password ******");
+/// ```
+#[cfg(feature = "std")]
+pub fn set_debug_post_processor(processor: Box<PostProcessor>) {
+    let mut global = POST_PROCESSOR
+        .get()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    *global = Some(Arc::from(processor));
+}
+
+// Write the output of `render` to `f`, through the post-processor if one is
+// installed.
+fn post_processed(
+    f: &mut fmt::Formatter,
+    render: &dyn Fn(&mut fmt::Formatter) -> fmt::Result,
+) -> fmt::Result {
+    #[cfg(feature = "std")]
+    {
+        let processor = POST_PROCESSOR
+            .get()
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone();
+        if let Some(processor) = processor {
+            struct Render<'a>(&'a dyn Fn(&mut fmt::Formatter) -> fmt::Result);
+
+            impl Debug for Render<'_> {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    (self.0)(f)
                 }
-                backtrace.truncate(backtrace.trim_end().len());
-                write!(f, "\n\n{}", backtrace)?;
             }
+
+            // Render with the flags that select the layout.
+            let mut output = String::new();
+            if f.alternate() {
+                write!(output, "{:#?}", Render(render))?;
+            } else if f.sign_plus() {
+                write!(output, "{:+?}", Render(render))?;
+            } else {
+                write!(output, "{:?}", Render(render))?;
+            }
+            processor(&mut output);
+            return f.write_str(&output);
         }
+    }
 
-        Ok(())
+    render(f)
+}
+
+impl ErrorImpl<()> {
+    pub(crate) fn display(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return self.display_joined(f, ": ");
+        }
+
+        write!(f, "{}", self.error())
+    }
+
+    pub(crate) fn display_joined(&self, f: &mut fmt::Formatter, separator: &str) -> fmt::Result {
+        fmt_joined(self.chain(), f, separator)
+    }
+
+    pub(crate) fn display_indexed(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_indexed(self.chain(), f)
+    }
+
+    pub(crate) fn debug(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.debug_with_color(f, crate::color::enabled())
+    }
+
+    pub(crate) fn backtrace_string(&self) -> Option<String> {
+        self.with_info(false, formatted_backtrace)
+    }
+
+    pub(crate) fn markdown(&self) -> String {
+        self.with_info(false, markdown)
+    }
+
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn rendered_backtrace(&self) -> Option<String> {
+        self.with_info(false, rendered_backtrace)
+    }
+
+    pub(crate) fn debug_with_color(&self, f: &mut fmt::Formatter, color: bool) -> fmt::Result {
+        post_processed(f, &|f| self.debug_unprocessed(f, color))
     }
+
+    // The Debug representation without the post-processor applied, for
+    // errors rendered as part of another error's.
+    pub(crate) fn debug_unprocessed(&self, f: &mut fmt::Formatter, color: bool) -> fmt::Result {
+        if f.alternate() {
+            return Debug::fmt(self.error(), f);
+        }
+
+        self.with_info(color, |info| fmt_with_formatter(info, f))
+    }
+
+    pub(crate) fn debug_with_formatter(
+        &self,
+        f: &mut fmt::Formatter,
+        formatter: &dyn ErrorFormatter,
+    ) -> fmt::Result {
+        post_processed(f, &|f| {
+            if f.alternate() {
+                return Debug::fmt(self.error(), f);
+            }
+
+            self.with_info(crate::color::enabled(), |info| formatter.fmt_error(info, f))
+        })
+    }
+
+    fn with_info<R>(&self, color: bool, f: impl FnOnce(&ErrorInfo) -> R) -> R {
+        // Take copies of the global settings rather than holding the locks
+        // while formatting, since the errors being formatted may themselves
+        // format other errors.
+        #[cfg(feature = "std")]
+        let config = CONFIG
+            .get()
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone();
+        #[cfg(not(feature = "std"))]
+        let config = FormatConfig::new();
+
+        let info = ErrorInfo {
+            inner: self,
+            error: self.error(),
+            extensions: &self.extensions,
+            #[cfg(backtrace)]
+            backtrace: self.backtrace(),
+            color,
+            config: &config,
+        };
+        f(&info)
+    }
+}
+
+// Format with the formatter for the flags of `f`, or the one set globally,
+// or the one named by the environment.
+fn fmt_with_formatter(info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+    #[cfg(feature = "std")]
+    {
+        if f.sign_plus() {
+            return RootCauseLast.fmt_error(info, f);
+        }
+
+        let global = FORMATTER
+            .get()
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone();
+        if let Some(formatter) = global {
+            return formatter.fmt_error(info, f);
+        }
+
+        let from_env = ENV_FORMATTER.get();
+        if let Some(formatter) = from_env {
+            return formatter.fmt_error(info, f);
+        }
+    }
+
+    RootCauseFirst.fmt_error(info, f)
 }
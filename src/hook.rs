@@ -0,0 +1,77 @@
+use crate::lazy::Lazy;
+use crate::Error;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+type Hook = dyn Fn(&Error) + Send + Sync;
+
+static HOOK: Lazy<RwLock<Option<Arc<Hook>>>> = lazy!(RwLock::new(None));
+
+// Set once a hook is installed, so that constructing an error does not take
+// the lock in the common case of there being no hook.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static IN_HOOK: Cell<bool> = Cell::new(false);
+}
+
+/// Register a callback to be invoked with every `Error` as it is constructed,
+/// for example to count or sample failures for telemetry.
+///
+/// The hook runs once for each error created by [`anyhow!`], [`bail!`],
+/// [`Error::new`], a `?` conversion, and once more each time context is
+/// added, since that creates a new outer error. Errors constructed while the
+/// hook is running on the same thread do not invoke it again, so the hook can
+/// create errors of its own without recursing. Registering a hook replaces
+/// the previous one.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static ERRORS: AtomicUsize = AtomicUsize::new(0);
+///
+/// anyhow::set_error_hook(Box::new(|_error| {
+///     ERRORS.fetch_add(1, Ordering::Relaxed);
+/// }));
+/// ```
+pub fn set_error_hook(hook: Box<Hook>) {
+    let mut global = HOOK
+        .get()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    *global = Some(Arc::from(hook));
+    INSTALLED.store(true, Ordering::Release);
+}
+
+pub(crate) fn observe(error: &Error) {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return;
+    }
+
+    // Take a copy of the hook rather than holding the lock while it runs, so
+    // that the hook may replace itself.
+    let hook = HOOK
+        .get()
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .clone();
+    let hook = match hook {
+        Some(hook) => hook,
+        None => return,
+    };
+
+    let _ = IN_HOOK.try_with(|in_hook| {
+        if in_hook.replace(true) {
+            return;
+        }
+        struct Reset<'a>(&'a Cell<bool>);
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                self.0.set(false);
+            }
+        }
+        let _reset = Reset(in_hook);
+        hook(error);
+    });
+}
@@ -47,9 +47,11 @@
 use crate::Error;
 use core::fmt::{Debug, Display};
 
-#[cfg(feature = "std")]
-use crate::StdError;
+#[cfg(any(feature = "std", error_in_core))]
+use crate::{alloc::Box, StdError};
 
+#[cfg(backtrace_crate)]
+use crate::backtrace::Backtrace;
 #[cfg(backtrace)]
 use std::backtrace::Backtrace;
 
@@ -65,11 +67,12 @@ pub trait AdhocKind: Sized {
 impl<T> AdhocKind for &T where T: ?Sized + Display + Debug + Send + Sync + 'static {}
 
 impl Adhoc {
+    #[cfg_attr(feature = "track-caller", track_caller)]
     pub fn new<M>(self, message: M) -> Error
     where
         M: Display + Debug + Send + Sync + 'static,
     {
-        Error::from_adhoc(message, backtrace!())
+        Error::from_adhoc(message, backtrace!()).at_caller()
     }
 }
 
@@ -85,18 +88,19 @@ pub trait TraitKind: Sized {
 impl<E> TraitKind for E where E: Into<Error> {}
 
 impl Trait {
+    #[cfg_attr(feature = "track-caller", track_caller)]
     pub fn new<E>(self, error: E) -> Error
     where
         E: Into<Error>,
     {
-        error.into()
+        error.into().at_caller()
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 pub struct Boxed;
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 pub trait BoxedKind: Sized {
     #[inline]
     fn anyhow_kind(&self) -> Boxed {
@@ -104,13 +108,14 @@ pub trait BoxedKind: Sized {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl BoxedKind for Box<dyn StdError + Send + Sync> {}
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 impl Boxed {
+    #[cfg_attr(feature = "track-caller", track_caller)]
     pub fn new(self, error: Box<dyn StdError + Send + Sync>) -> Error {
         let backtrace = backtrace_if_absent!(error);
-        Error::from_boxed(error, backtrace)
+        Error::from_boxed_error(error, backtrace).at_caller()
     }
 }
@@ -0,0 +1,45 @@
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+// A global value created by `init` on first use and never dropped. This takes
+// the place of a static RwLock or OnceLock, whose constructors are not const
+// on the minimum supported Rust version.
+pub(crate) struct Lazy<T> {
+    pub(crate) ptr: AtomicPtr<T>,
+    pub(crate) init: fn() -> T,
+}
+
+macro_rules! lazy {
+    ($init:expr) => {
+        $crate::lazy::Lazy {
+            ptr: core::sync::atomic::AtomicPtr::new(core::ptr::null_mut()),
+            init: || $init,
+        }
+    };
+}
+
+impl<T> Lazy<T>
+where
+    T: Send + Sync,
+{
+    pub(crate) fn get(&'static self) -> &'static T {
+        let mut value = self.ptr.load(Ordering::Acquire);
+        if value.is_null() {
+            let new = Box::into_raw(Box::new((self.init)()));
+            value = match self.ptr.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new,
+                // Another thread got there first, so this one's is not needed.
+                Err(existing) => {
+                    drop(unsafe { Box::from_raw(new) });
+                    existing
+                }
+            };
+        }
+        unsafe { &*value }
+    }
+}
@@ -123,6 +123,14 @@
 //! - A backtrace is captured and printed with the error if the underlying error
 //!   type does not already provide its own. In order to see backtraces, the
 //!   `RUST_LIB_BACKTRACE=1` environment variable must be defined.
+//!   On a stable compiler, enable the `backtrace-crate` feature to capture
+//!   backtraces through the [`backtrace`] crate instead of std, and
+//!   `set_backtrace_filter` to choose which of their frames are shown.
+//!   Backtraces are never captured when building for WebAssembly, where they
+//!   are not supported, or with the `wasm` feature enabled, which does the
+//!   same for other targets.
+//!
+//!   [`backtrace`]: https://docs.rs/backtrace
 //!
 //! - Anyhow works with any error type that has an impl of `std::error::Error`,
 //!   including ones defined in your crate. We do not bundle a `derive(Error)`
@@ -172,13 +180,16 @@
 //! anyhow = { version = "1.0", default-features = false }
 //! ```
 //!
-//! Since the `?`-based error conversions would normally rely on the
-//! `std::error::Error` trait which is only available through std, no_std mode
-//! will require an explicit `.map_err(Error::msg)` when working with a
-//! non-Anyhow error type inside a function that returns Anyhow's error type.
+//! On Rust 1.81 and newer, no_std mode builds on the `core::error::Error` trait,
+//! so `?`, `.context(...)` and `Error::chain` work with any error type that
+//! implements it. On older compilers the `?`-based error conversions are
+//! unavailable in no_std mode, and working with a non-Anyhow error type inside
+//! a function that returns Anyhow's error type requires an explicit
+//! `.map_err(Error::msg)`.
 
 #![doc(html_root_url = "https://docs.rs/anyhow/1.0.25")]
 #![cfg_attr(backtrace, feature(backtrace))]
+#![cfg_attr(provide, feature(error_generic_member_access))]
 #![cfg_attr(not(feature = "std"), no_std)]
 #![allow(
     clippy::needless_doctest_main,
@@ -190,35 +201,79 @@ mod alloc {
     #[cfg(not(feature = "std"))]
     extern crate alloc;
 
+    #[cfg(not(feature = "std"))]
+    pub use alloc::borrow::Cow;
+
     #[cfg(not(feature = "std"))]
     pub use alloc::boxed::Box;
 
+    #[cfg(not(feature = "std"))]
+    pub use alloc::string::String;
+
+    #[cfg(not(feature = "std"))]
+    pub use alloc::vec::{self, Vec};
+
+    #[cfg(feature = "std")]
+    pub use std::borrow::Cow;
+
     #[cfg(feature = "std")]
     pub use std::boxed::Box;
+
+    #[cfg(feature = "std")]
+    pub use std::string::String;
+
+    #[cfg(feature = "std")]
+    pub use std::vec::{self, Vec};
 }
 
+// The features named serde and termcolor enable these dependencies, which are
+// renamed in Cargo.toml because a feature cannot share its name with an
+// optional dependency.
+#[cfg(feature = "serde")]
+extern crate serde_crate as serde;
+#[cfg(feature = "termcolor")]
+extern crate termcolor_crate as termcolor;
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod lazy;
 #[macro_use]
 mod backtrace;
+#[cfg(any(feature = "std", error_in_core))]
+mod aggregate;
 mod chain;
+mod color;
 mod context;
 mod error;
+mod extensions;
+mod fields;
 mod fmt;
+#[cfg(feature = "std")]
+mod hook;
 mod kind;
 mod macros;
+mod metadata;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(any(feature = "std", error_in_core))]
+mod snapshot;
 mod wrapper;
 
-use crate::alloc::Box;
+use crate::alloc::{Box, String};
 use crate::error::ErrorImpl;
 use core::fmt::Display;
 use core::mem::ManuallyDrop;
 
-#[cfg(not(feature = "std"))]
+#[cfg(not(any(feature = "std", error_in_core)))]
 use core::fmt::Debug;
 
 #[cfg(feature = "std")]
 use std::error::Error as StdError;
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(not(feature = "std"), error_in_core))]
+use core::error::Error as StdError;
+
+#[cfg(not(any(feature = "std", error_in_core)))]
 trait StdError: Debug + Display {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
@@ -227,6 +282,25 @@ trait StdError: Debug + Display {
 
 pub use anyhow as format_err;
 
+pub use crate::fields::FieldVisitor;
+pub use crate::metadata::{ErrorBuilder, Severity};
+
+#[cfg(feature = "color")]
+pub use crate::color::set_color;
+
+#[cfg(feature = "std")]
+pub use crate::fmt::{
+    compact_backtrace, formatter_from_env, set_debug_post_processor, set_format_config,
+    set_formatter, ErrorFormatter, ErrorInfo, FormatConfig, GutterStyle, Indented, RootCauseFirst,
+    RootCauseLast,
+};
+
+#[cfg(feature = "backtrace-crate")]
+pub use crate::backtrace::{set_backtrace_filter, BacktraceFrame};
+
+#[cfg(feature = "std")]
+pub use crate::hook::set_error_hook;
+
 /// The `Error` type, a wrapper around a dynamic error type.
 ///
 /// `Error` works a lot like `Box<dyn std::error::Error>`, but with these
@@ -284,6 +358,16 @@ pub use anyhow as format_err;
 ///    7: _start
 /// ```
 ///
+/// To print the root cause first and the outermost context last, which reads
+/// more naturally in some logs, use "{:+?}".
+///
+/// ```console
+/// Error: No such file or directory (os error 2)
+///
+/// Which caused:
+///     Failed to read instrs from ./path/to/instrs.json
+/// ```
+///
 /// To see a conventional struct-style Debug representation, use "{:#?}".
 ///
 /// ```console
@@ -325,7 +409,8 @@ pub struct Error {
 
 /// Iterator of a chain of source errors.
 ///
-/// This type is the iterator returned by [`Error::chain`].
+/// This type is the iterator returned by [`Error::chain`]. Displaying it
+/// prints the errors it has left to yield on one line, joined by `": "`.
 ///
 /// # Example
 ///
@@ -342,7 +427,7 @@ pub struct Error {
 ///     None
 /// }
 /// ```
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", error_in_core))]
 #[derive(Clone)]
 pub struct Chain<'a> {
     state: crate::chain::ChainState<'a>,
@@ -552,6 +637,118 @@ pub trait Context<T, E>: context::private::Sealed {
     where
         C: Display + Send + Sync + 'static,
         F: FnOnce() -> C;
+
+    /// Wrap the error value with additional context, and capture a backtrace
+    /// here if the error does not already hold one.
+    ///
+    /// This is meant for errors from libraries that do not capture
+    /// backtraces: the backtrace then points at where the context was added,
+    /// which is usually close to the failure. It is captured regardless of
+    /// `RUST_LIB_BACKTRACE`, like [`Error::ensure_backtrace`], and only on
+    /// toolchains where anyhow captures backtraces at all.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn load(path: &str) -> Result<String> {
+    ///     fs::read_to_string(path).context_capture("failed to load config")
+    /// }
+    /// ```
+    fn context_capture<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value with additional context only if `condition` is
+    /// true; otherwise the error is passed through as is.
+    ///
+    /// The context is evaluated lazily, only once an error occurs and the
+    /// condition holds. On `Option` there is no error to pass through, so a
+    /// `None` always becomes an error displaying the context.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn read(path: &str, verbose: bool) -> Result<String> {
+    ///     fs::read_to_string(path).context_if(verbose, || format!("failed to read {}", path))
+    /// }
+    ///
+    /// let error = read("/nonexistent", false).unwrap_err();
+    /// assert_eq!(error.chain().count(), 1);
+    ///
+    /// let error = read("/nonexistent", true).unwrap_err();
+    /// assert_eq!(error.to_string(), "failed to read /nonexistent");
+    /// ```
+    fn context_if<C, F>(self, condition: bool, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+
+    /// Wrap the error value with several lines of context at once, as a
+    /// single layer of the chain.
+    ///
+    /// The lines are displayed one below the other, so that in the Debug
+    /// representation they share one numbered entry among the causes. This
+    /// keeps related facts together and adds one layer rather than one for
+    /// each line.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn read(path: &str, user: &str) -> Result<String> {
+    ///     fs::read_to_string(path).context_all(vec![
+    ///         format!("failed to read {}", path),
+    ///         format!("on behalf of {}", user),
+    ///     ])
+    /// }
+    ///
+    /// let error = read("/nonexistent", "alice").unwrap_err();
+    /// assert_eq!(error.to_string(), "failed to read /nonexistent\non behalf of alice");
+    /// assert_eq!(error.chain().count(), 2);
+    /// ```
+    fn context_all<I>(self, lines: I) -> Result<T, Error>
+    where
+        I: IntoIterator<Item = String>;
+
+    /// Wrap the error value in an error of type `C` that is evaluated lazily
+    /// only once an error does occur.
+    ///
+    /// The new layer displays as `C` and can be downcast to `C` later, while
+    /// its `source()` is the original error, so the chain is kept.
+    ///
+    /// A layer has only one source, so a `source()` that `C` has of its own
+    /// is lost: the chain goes on from `C` to the original error, and the
+    /// source of `C` is not part of it. On an `Option` there is no original
+    /// error, and the chain goes on with the source of `C`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    /// use thiserror::Error;
+    ///
+    /// #[derive(Error, Debug)]
+    /// #[error("failed to load {path}")]
+    /// struct LoadError {
+    ///     path: String,
+    /// }
+    ///
+    /// fn load(path: &str) -> Result<String> {
+    ///     fs::read_to_string(path).with_context_err(|| LoadError {
+    ///         path: path.to_owned(),
+    ///     })
+    /// }
+    ///
+    /// let error = load("/nonexistent").unwrap_err();
+    /// assert_eq!(error.downcast_ref::<LoadError>().unwrap().path, "/nonexistent");
+    /// assert!(error.root_cause().is::<std::io::Error>());
+    /// ```
+    #[cfg(any(feature = "std", error_in_core))]
+    fn with_context_err<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: StdError + Send + Sync + 'static,
+        F: FnOnce() -> C;
 }
 
 // Not public API. Referenced by macro-generated code.
@@ -560,23 +757,35 @@ pub mod private {
     use crate::Error;
     use core::fmt::{Debug, Display};
 
+    #[cfg(backtrace_crate)]
+    use crate::backtrace::Backtrace;
     #[cfg(backtrace)]
     use std::backtrace::Backtrace;
 
     pub use core::result::Result::Err;
 
+    pub use crate::context::context_at;
+
+    pub fn boundary<T, E>(result: Result<T, E>) -> Result<T, Error>
+    where
+        E: Into<Error>,
+    {
+        result.map_err(|error| error.into().ensure_backtrace())
+    }
+
     #[doc(hidden)]
     pub mod kind {
         pub use crate::kind::{AdhocKind, TraitKind};
 
-        #[cfg(feature = "std")]
+        #[cfg(any(feature = "std", error_in_core))]
         pub use crate::kind::BoxedKind;
     }
 
+    #[cfg_attr(feature = "track-caller", track_caller)]
     pub fn new_adhoc<M>(message: M) -> Error
     where
         M: Display + Debug + Send + Sync + 'static,
     {
-        Error::from_adhoc(message, backtrace!())
+        Error::from_adhoc(message, backtrace!()).at_caller()
     }
 }
@@ -47,8 +47,61 @@
 /// #     Ok(())
 /// # }
 /// ```
+///
+/// A single argument other than a string literal is resolved as follows, at
+/// compile time:
+///
+/// - an `anyhow::Error` is returned as is;
+/// - a type implementing `std::error::Error` is converted with `From`, so the
+///   error keeps its `source()` chain and can be downcast to its own type,
+///   exactly as `return Err(err.into())` would;
+/// - a `Box<dyn Error + Send + Sync>` is adopted without boxing it again;
+/// - any other type implementing `Display` and `Debug`, such as a `String`,
+///   becomes the message of a new error.
+///
+/// So `bail!(err)` with an error value never flattens that error into a
+/// string. To do that on purpose, write `bail!("{}", err)`.
+///
+/// ```
+/// # use anyhow::{bail, Result};
+/// # use std::io;
+/// #
+/// fn open() -> Result<()> {
+///     let err = io::Error::new(io::ErrorKind::NotFound, "no config");
+///     bail!(err);
+/// }
+///
+/// let error = open().unwrap_err();
+/// assert_eq!(error.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+/// ```
+///
+/// The error can be given a [severity][crate::Error::with_severity] or be
+/// marked [retryable][crate::Error::retryable] by leading `severity:` and
+/// `retryable:` arguments, in either order, before the usual arguments.
+///
+/// ```
+/// # use anyhow::{bail, Result, Severity};
+/// #
+/// # fn main() -> Result<()> {
+/// #     let status = 200;
+/// #
+/// if status == 503 {
+///     bail!(retryable: true, "service unavailable: {}", status);
+/// }
+/// if status >= 500 {
+///     bail!(severity: Severity::Error, retryable: false, "server error: {}", status);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
 #[macro_export]
 macro_rules! bail {
+    (retryable: $retryable:expr, $($rest:tt)+) => {
+        return $crate::private::Err($crate::__anyhow_metadata!(retryable: $retryable, $($rest)+));
+    };
+    (severity: $severity:expr, $($rest:tt)+) => {
+        return $crate::private::Err($crate::__anyhow_metadata!(severity: $severity, $($rest)+));
+    };
     ($msg:literal $(,)?) => {
         return $crate::private::Err($crate::anyhow!($msg));
     };
@@ -60,6 +113,88 @@ macro_rules! bail {
     };
 }
 
+// Not public API. Peels the metadata arguments off the front of bail! and
+// applies them to the error made from the rest.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_metadata {
+    (retryable: $retryable:expr, $($rest:tt)+) => {
+        $crate::__anyhow_metadata!($($rest)+).retryable($retryable)
+    };
+    (severity: $severity:expr, $($rest:tt)+) => {
+        $crate::__anyhow_metadata!($($rest)+).with_severity($severity)
+    };
+    ($($rest:tt)+) => {
+        $crate::anyhow!($($rest)+)
+    };
+}
+
+/// Convert the error of a `Result` into `anyhow::Error`, capturing a
+/// backtrace at this point if the error does not already hold one.
+///
+/// This evaluates to a `Result<T, anyhow::Error>`, to be followed by `?`.
+/// Combined with leaving `RUST_LIB_BACKTRACE` unset, it limits the cost of
+/// capturing backtraces to the errors that cross an annotated boundary, such
+/// as the public API of a library or the request handler of a server, rather
+/// than every error created along the way.
+///
+/// See [`Error::ensure_backtrace`][crate::Error::ensure_backtrace].
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{boundary, Result};
+/// # use std::fs;
+/// #
+/// pub fn load(path: &str) -> Result<String> {
+///     let config = boundary!(fs::read_to_string(path))?;
+///     Ok(config)
+/// }
+/// #
+/// # assert!(load("/nonexistent").is_err());
+/// ```
+#[macro_export]
+macro_rules! boundary {
+    ($result:expr $(,)?) => {
+        $crate::private::boundary($result)
+    };
+}
+
+/// Return early with an error that carries a numeric code.
+///
+/// This macro is equivalent to `bail!` followed by
+/// [`with_code`][crate::Error::with_code]. The code comes first, and the
+/// remaining arguments are the same as for `bail!`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{bail_code, Result};
+/// #
+/// # const ENOENT: i32 = 2;
+/// #
+/// # fn main() -> Result<()> {
+/// #     let path = "config.toml";
+/// #
+/// if path.is_empty() {
+///     bail_code!(ENOENT, "no such file: {:?}", path);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! bail_code {
+    ($code:expr, $msg:literal $(,)?) => {
+        return $crate::private::Err($crate::anyhow!($msg).with_code($code));
+    };
+    ($code:expr, $err:expr $(,)?) => {
+        return $crate::private::Err($crate::anyhow!($err).with_code($code));
+    };
+    ($code:expr, $fmt:expr, $($arg:tt)*) => {
+        return $crate::private::Err($crate::anyhow!($fmt, $($arg)*).with_code($code));
+    };
+}
+
 /// Return early with an error if a condition is not satisfied.
 ///
 /// This macro is equivalent to `if !$cond { return Err(From::from($err)); }`.
@@ -104,8 +239,42 @@ macro_rules! bail {
 /// #     Ok(())
 /// # }
 /// ```
+///
+/// With `else =>`, the expression that follows is evaluated only if the
+/// condition fails, and is converted into the function's error type with
+/// `From`. This is useful when the error needs to be built from values that are
+/// expensive to compute or when the function returns an error type other than
+/// `anyhow::Error`.
+///
+/// ```
+/// # use anyhow::{ensure, Result};
+/// # use thiserror::Error;
+/// #
+/// #[derive(Error, Debug)]
+/// #[error("expected {expected} bytes, got {actual}")]
+/// struct LengthError {
+///     expected: usize,
+///     actual: usize,
+/// }
+///
+/// fn check(bytes: &[u8]) -> Result<()> {
+///     ensure!(bytes.len() == 4, else => LengthError {
+///         expected: 4,
+///         actual: bytes.len(),
+///     });
+///     Ok(())
+/// }
+///
+/// let error = check(b"abc").unwrap_err();
+/// assert_eq!(error.downcast_ref::<LengthError>().unwrap().actual, 3);
+/// ```
 #[macro_export]
 macro_rules! ensure {
+    ($cond:expr, else => $err:expr $(,)?) => {
+        if !$cond {
+            return $crate::private::Err(::core::convert::From::from($err));
+        }
+    };
     ($cond:expr, $msg:literal $(,)?) => {
         if !$cond {
             return $crate::private::Err($crate::anyhow!($msg));
@@ -123,12 +292,191 @@ macro_rules! ensure {
     };
 }
 
+/// Return early with an error if two expressions are not equal to each other.
+///
+/// Analogously to `assert_eq!`, `ensure_eq!` compares the two values with
+/// `PartialEq` and, if they differ, exits the function with an error showing
+/// both values with their `Debug` representation. The message is optional:
+/// without one, the error reads like ``Condition failed: `a == b` (1 vs 2)``,
+/// and with one it is followed by the values, as in `bad length (3 vs 4)`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_eq, Result};
+/// #
+/// fn check_header(magic: &[u8]) -> Result<()> {
+///     ensure_eq!(magic, b"\x7fELF", "not an ELF file");
+///     Ok(())
+/// }
+///
+/// let error = check_header(b"MZ").unwrap_err();
+/// assert_eq!(error.to_string(), "not an ELF file ([77, 90] vs [127, 69, 76, 70])");
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    return $crate::private::Err($crate::private::new_adhoc(format!(
+                        "Condition failed: `{} == {}` ({:?} vs {:?})",
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right,
+                    )));
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    return $crate::private::Err($crate::private::new_adhoc(format!(
+                        "{} ({:?} vs {:?})",
+                        format_args!($($arg)+),
+                        left,
+                        right,
+                    )));
+                }
+            }
+        }
+    };
+}
+
+/// Return early with an error if two expressions are equal to each other.
+///
+/// This is the counterpart of [`ensure_eq!`], with the same optional message.
+/// Without one, the error reads like ``Condition failed: `a != b` (1 vs 1)``.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_ne, Result};
+/// #
+/// fn rename(from: &str, to: &str) -> Result<()> {
+///     ensure_ne!(from, to, "cannot rename {} to itself", from);
+///     Ok(())
+/// }
+///
+/// let error = rename("a.txt", "a.txt").unwrap_err();
+/// assert_eq!(error.to_string(), "cannot rename a.txt to itself (\"a.txt\" vs \"a.txt\")");
+/// ```
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    return $crate::private::Err($crate::private::new_adhoc(format!(
+                        "Condition failed: `{} != {}` ({:?} vs {:?})",
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right,
+                    )));
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    return $crate::private::Err($crate::private::new_adhoc(format!(
+                        "{} ({:?} vs {:?})",
+                        format_args!($($arg)+),
+                        left,
+                        right,
+                    )));
+                }
+            }
+        }
+    };
+}
+
+/// Add context to a `Result`, formatting the message only if it is an error.
+///
+/// This macro is equivalent to
+/// `$result.with_context(|| format!($fmt, $($arg)*))` from the
+/// [`Context`][crate::Context] trait, so the arguments are not formatted, and
+/// expressions among them not evaluated, on the success path. A string literal
+/// alone is attached as is, and any other single expression is evaluated
+/// lazily and attached as the context.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{context, Result};
+/// # use std::fs;
+/// #
+/// fn read_config(path: &str) -> Result<String> {
+///     let config = context!(fs::read_to_string(path), "failed to read {}", path)?;
+///     Ok(config)
+/// }
+/// #
+/// # assert!(read_config("/nonexistent").is_err());
+/// ```
+#[macro_export]
+macro_rules! context {
+    ($result:expr, $msg:literal $(,)?) => {
+        $crate::Context::context($result, $msg)
+    };
+    ($result:expr, $context:expr $(,)?) => {
+        $crate::Context::with_context($result, || $context)
+    };
+    ($result:expr, $fmt:expr, $($arg:tt)*) => {
+        $crate::Context::with_context($result, || format!($fmt, $($arg)*))
+    };
+}
+
+/// Add context to a `Result` like [`context!`], also recording the file and
+/// line where it was added.
+///
+/// The location is shown after the context in the Debug representation of
+/// the error, as in `failed to read config at src/main.rs:12`. Context added
+/// in any other way has no location. The location is recorded with Rust 1.46
+/// or newer; older compilers add the context without it.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{context_at, Result};
+/// # use std::fs;
+/// #
+/// fn read_config(path: &str) -> Result<String> {
+///     let config = context_at!(fs::read_to_string(path), "failed to read {}", path)?;
+///     Ok(config)
+/// }
+/// #
+/// # assert!(read_config("/nonexistent").is_err());
+/// ```
+#[macro_export]
+macro_rules! context_at {
+    ($result:expr, $context:expr $(,)?) => {
+        $crate::private::context_at($result, || $context)
+    };
+    ($result:expr, $fmt:expr, $($arg:tt)*) => {
+        $crate::private::context_at($result, || format!($fmt, $($arg)*))
+    };
+}
+
 /// Construct an ad-hoc error from a string.
 ///
 /// This evaluates to an `Error`. It can take either just a string, or a format
 /// string with arguments. It also can take any custom type which implements
 /// `Debug` and `Display`.
 ///
+/// With the `track-caller` feature enabled, the file and line of the macro
+/// call are recorded in the error. They are shown after its message in the
+/// Debug representation, as in `missing attribute (at src/parse.rs:10)`,
+/// unless turned off with
+/// [`FormatConfig::show_creation_location`][crate::FormatConfig::show_creation_location].
+/// This also applies to errors created by [`bail!`] and [`ensure!`]. The
+/// feature requires Rust 1.46 or newer.
+///
 /// # Example
 ///
 /// ```
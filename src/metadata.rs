@@ -0,0 +1,471 @@
+use crate::alloc::{String, Vec};
+#[cfg(any(backtrace, backtrace_crate))]
+use crate::backtrace::Backtrace;
+use crate::Error;
+use core::fmt::{self, Display};
+use core::iter;
+
+#[cfg(feature = "timestamp")]
+use std::time::SystemTime;
+
+/// How serious an error is, for routing it in a logging pipeline.
+///
+/// Attach a severity with [`Error::with_severity`]. When present, the Debug
+/// representation of the error is prefixed with the severity, as in
+/// `[WARN] disk almost full`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Trace,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Trace => "TRACE",
+            Severity::Info => "INFO",
+            Severity::Warn => "WARN",
+            Severity::Error => "ERROR",
+            Severity::Fatal => "FATAL",
+        })
+    }
+}
+
+// Extension recording Error::retryable.
+struct Retryable(bool);
+
+// Extension recording Error::with_code.
+pub(crate) struct Code(pub i32);
+
+// Extension recording the thread an error was created on, by name or, for an
+// unnamed thread, by id.
+#[cfg(feature = "thread-origin")]
+pub(crate) struct ThreadOrigin(pub String);
+
+#[cfg(feature = "thread-origin")]
+impl ThreadOrigin {
+    pub(crate) fn current() -> Self {
+        let thread = std::thread::current();
+        match thread.name() {
+            Some(name) => ThreadOrigin(name.to_owned()),
+            None => ThreadOrigin(format!("{:?}", thread.id())),
+        }
+    }
+}
+
+// Extension recording when an error was created.
+#[cfg(feature = "timestamp")]
+struct CreatedAt(SystemTime);
+
+// Extension recording Error::with_section, in insertion order.
+#[derive(Clone)]
+pub(crate) struct Sections(pub Vec<Section>);
+
+#[derive(Clone)]
+pub(crate) struct Section {
+    pub header: String,
+    pub body: String,
+}
+
+// Extension recording Error::with_help_url, in insertion order.
+#[derive(Clone)]
+pub(crate) struct HelpUrls(pub Vec<String>);
+
+// Extension recording Error::attach_named, in insertion order.
+#[derive(Clone)]
+struct Attachments(Vec<(String, Vec<u8>)>);
+
+impl Error {
+    /// Tag this error with a severity, replacing any severity set earlier
+    /// including one set before context was added.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Severity};
+    ///
+    /// let error = anyhow!("disk almost full").with_severity(Severity::Warn);
+    /// assert_eq!(error.severity(), Some(Severity::Warn));
+    ///
+    /// let error = error.context("failed to write log").with_severity(Severity::Error);
+    /// assert_eq!(error.severity(), Some(Severity::Error));
+    /// ```
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.insert_ext(severity);
+        self
+    }
+
+    /// The severity most recently set by [`with_severity`][Error::with_severity],
+    /// or `None` if there is none.
+    pub fn severity(&self) -> Option<Severity> {
+        self.get_ext::<Severity>().cloned()
+    }
+
+    /// Mark whether the operation that produced this error is worth retrying.
+    ///
+    /// The flag is carried along when context is added, so it can be set where
+    /// a transient failure is detected and read by a retry loop further up.
+    /// Calling this again, including after adding context, overrides the
+    /// earlier value.
+    ///
+    /// ```
+    /// # use anyhow::{anyhow, Context, Result};
+    /// #
+    /// fn connect() -> Result<()> {
+    ///     Err(anyhow!("connection reset").retryable(true))
+    /// }
+    ///
+    /// let error = connect().context("failed to sync").unwrap_err();
+    /// assert!(error.is_retryable());
+    /// ```
+    pub fn retryable(mut self, yes: bool) -> Self {
+        self.insert_ext(Retryable(yes));
+        self
+    }
+
+    /// Whether this error was marked as [`retryable`][Error::retryable].
+    /// Errors that were never marked are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        self.get_ext::<Retryable>()
+            .map_or(false, |retryable| retryable.0)
+    }
+
+    /// Attach a numeric code to this error, for mapping it onto an FFI return
+    /// value or an RPC status.
+    ///
+    /// The code is carried along when context is added and appears in the
+    /// Debug representation after the first line, as in `not found (code 5)`.
+    /// See also [`bail_code!`].
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("not found").with_code(5);
+    /// assert_eq!(error.code(), Some(5));
+    /// ```
+    pub fn with_code(mut self, code: i32) -> Self {
+        self.insert_ext(Code(code));
+        self
+    }
+
+    /// The code most recently set by [`with_code`][Error::with_code], or
+    /// `None` if there is none.
+    pub fn code(&self) -> Option<i32> {
+        self.get_ext::<Code>().map(|code| code.0)
+    }
+
+    /// The name of the thread this error was created on, or its id, as in
+    /// `ThreadId(4)`, if the thread has no name.
+    ///
+    /// This is the thread where the innermost error was turned into an
+    /// `Error`, even if context was added to it on another thread later.
+    /// Recorded only with the `thread-origin` feature enabled. See also
+    /// [`FormatConfig::show_thread`][crate::FormatConfig::show_thread].
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use std::thread;
+    ///
+    /// let error = thread::Builder::new()
+    ///     .name("worker-2".to_owned())
+    ///     .spawn(|| anyhow!("oh no!"))
+    ///     .unwrap()
+    ///     .join()
+    ///     .unwrap();
+    /// assert_eq!(error.origin_thread(), Some("worker-2"));
+    /// ```
+    #[cfg(feature = "thread-origin")]
+    pub fn origin_thread(&self) -> Option<&str> {
+        self.get_ext::<ThreadOrigin>()
+            .map(|origin| origin.0.as_str())
+    }
+
+    /// The time this error was created, recorded only with the `timestamp`
+    /// feature enabled.
+    ///
+    /// Like [`origin_thread`][Error::origin_thread], this is when the
+    /// innermost error was turned into an `Error`; adding context later does
+    /// not change it.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use std::time::SystemTime;
+    ///
+    /// let error = anyhow!("oh no!");
+    /// assert!(error.created_at().unwrap() <= SystemTime::now());
+    /// ```
+    #[cfg(feature = "timestamp")]
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.get_ext::<CreatedAt>().map(|created_at| created_at.0)
+    }
+
+    // Record where and when a new error was created. Context takes over the
+    // extensions of the error it wraps, these included, so an error that
+    // already has them is left alone.
+    #[cfg(any(feature = "thread-origin", feature = "timestamp"))]
+    pub(crate) fn record_origin(&mut self) {
+        #[cfg(feature = "thread-origin")]
+        {
+            if self.get_ext::<ThreadOrigin>().is_none() {
+                self.insert_ext(ThreadOrigin::current());
+            }
+        }
+        #[cfg(feature = "timestamp")]
+        {
+            if self.get_ext::<CreatedAt>().is_none() {
+                self.insert_ext(CreatedAt(SystemTime::now()));
+            }
+        }
+    }
+
+    /// Attach a labeled section of free-form text, such as a suggestion or a
+    /// note, to be printed after the causes and before the backtrace in the
+    /// Debug representation.
+    ///
+    /// Sections are carried along when context is added and are printed in
+    /// the order they were attached.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    ///
+    /// let error = anyhow!("config.toml not found")
+    ///     .with_section("Suggestion:", "run `app init` to create one".to_owned());
+    ///
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "config.toml not found\n\nSuggestion:\n    run `app init` to create one",
+    /// );
+    /// ```
+    pub fn with_section(mut self, header: &str, body: String) -> Self {
+        let section = Section {
+            header: header.into(),
+            body,
+        };
+        match self.inner.extensions.get_mut::<Sections>() {
+            Some(sections) => sections.0.push(section),
+            None => {
+                self.insert_ext(Sections(iter::once(section).collect()));
+            }
+        }
+        self
+    }
+
+    /// Attach a hint telling the user how to fix the problem, printed as a
+    /// `Suggestion:` section of the Debug representation. Each suggestion is
+    /// its own section, in the order they were added.
+    ///
+    /// Unlike context, suggestions are not part of the [chain][Error::chain]
+    /// of errors, so they stay out of the Display representation and out of
+    /// logs that record only the chain.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    ///
+    /// let error = anyhow!("output file exists").suggestion("try running with --force");
+    ///
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "output file exists\n\nSuggestion:\n    try running with --force",
+    /// );
+    /// ```
+    pub fn suggestion(self, text: impl Into<String>) -> Self {
+        self.with_section("Suggestion:", text.into())
+    }
+
+    /// Point to documentation about this error, printed as a `See:` line at
+    /// the end of the Debug representation, before the backtrace. Each URL
+    /// gets its own line, in the order they were added.
+    ///
+    /// When colors are on, the URL is written as a terminal hyperlink, which
+    /// terminals that support them make clickable.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    ///
+    /// let error = anyhow!("invalid manifest")
+    ///     .with_help_url("https://docs.example/errors/E123");
+    ///
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "invalid manifest\n\nSee: https://docs.example/errors/E123",
+    /// );
+    /// ```
+    pub fn with_help_url(mut self, url: impl Into<String>) -> Self {
+        let url = url.into();
+        match self.inner.extensions.get_mut::<HelpUrls>() {
+            Some(urls) => urls.0.push(url),
+            None => {
+                self.insert_ext(HelpUrls(iter::once(url).collect()));
+            }
+        }
+        self
+    }
+
+    /// Attach a named binary payload, such as a dump of the configuration or
+    /// the last lines of a log, for a crash reporter to upload along with the
+    /// error.
+    ///
+    /// Attachments are carried along when context is added, in the order
+    /// they were attached. They are never printed: read them back with
+    /// [`attachments`][Error::attachments].
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// # let config = anyhow::FormatConfig::new().show_backtrace(false);
+    /// # #[cfg(feature = "track-caller")]
+    /// # let config = config.show_creation_location(false);
+    /// # anyhow::set_format_config(config);
+    ///
+    /// let error = anyhow!("worker crashed").attach_named("log.txt", b"panic at 0x1f".to_vec());
+    ///
+    /// assert_eq!(format!("{:?}", error), "worker crashed");
+    /// assert_eq!(error.attachments()[0].0, "log.txt");
+    /// ```
+    pub fn attach_named(mut self, name: &str, data: Vec<u8>) -> Self {
+        let attachment = (String::from(name), data);
+        match self.inner.extensions.get_mut::<Attachments>() {
+            Some(attachments) => attachments.0.push(attachment),
+            None => {
+                self.insert_ext(Attachments(iter::once(attachment).collect()));
+            }
+        }
+        self
+    }
+
+    /// The payloads attached by [`attach_named`][Error::attach_named], as
+    /// pairs of name and data in the order they were attached.
+    pub fn attachments(&self) -> &[(String, Vec<u8>)] {
+        match self.get_ext::<Attachments>() {
+            Some(attachments) => &attachments.0,
+            None => &[],
+        }
+    }
+
+    /// Start building an error from a message, to set its metadata in one
+    /// place with the setters of [`ErrorBuilder`].
+    ///
+    /// ```
+    /// use anyhow::{Error, Severity};
+    ///
+    /// let error = Error::build("upstream unavailable")
+    ///     .code(503)
+    ///     .severity(Severity::Warn)
+    ///     .retryable(true)
+    ///     .help_url("https://docs.example/errors/upstream")
+    ///     .finish();
+    ///
+    /// assert_eq!(error.code(), Some(503));
+    /// assert!(error.is_retryable());
+    /// ```
+    pub fn build<M>(message: M) -> ErrorBuilder
+    where
+        M: Display + fmt::Debug + Send + Sync + 'static,
+    {
+        ErrorBuilder {
+            error: Error::from_adhoc(message, backtrace!()),
+        }
+    }
+
+    // Carry the metadata of this error over to a new error standing in for
+    // it, such as a snapshot.
+    #[cfg(any(feature = "std", error_in_core))]
+    pub(crate) fn copy_metadata(&self, to: &mut Error) {
+        if let Some(severity) = self.severity() {
+            to.insert_ext(severity);
+        }
+        if let Some(retryable) = self.get_ext::<Retryable>() {
+            to.insert_ext(Retryable(retryable.0));
+        }
+        if let Some(code) = self.code() {
+            to.insert_ext(Code(code));
+        }
+        if let Some(sections) = self.get_ext::<Sections>() {
+            to.insert_ext(sections.clone());
+        }
+        if let Some(urls) = self.get_ext::<HelpUrls>() {
+            to.insert_ext(urls.clone());
+        }
+        if let Some(attachments) = self.get_ext::<Attachments>() {
+            to.insert_ext(attachments.clone());
+        }
+        #[cfg(feature = "thread-origin")]
+        {
+            if let Some(origin) = self.get_ext::<ThreadOrigin>() {
+                to.insert_ext(ThreadOrigin(origin.0.clone()));
+            }
+        }
+        #[cfg(feature = "timestamp")]
+        {
+            if let Some(created_at) = self.created_at() {
+                to.insert_ext(CreatedAt(created_at));
+            }
+        }
+    }
+}
+
+/// An error under construction, returned by [`Error::build`].
+///
+/// Each setter does the same as the `Error` method of the same purpose, and
+/// [`finish`][ErrorBuilder::finish] returns the error.
+pub struct ErrorBuilder {
+    error: Error,
+}
+
+impl ErrorBuilder {
+    /// See [`Error::with_code`].
+    pub fn code(mut self, code: i32) -> Self {
+        self.error.insert_ext(Code(code));
+        self
+    }
+
+    /// See [`Error::with_severity`].
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.error.insert_ext(severity);
+        self
+    }
+
+    /// See [`Error::retryable`].
+    pub fn retryable(mut self, yes: bool) -> Self {
+        self.error.insert_ext(Retryable(yes));
+        self
+    }
+
+    /// See [`Error::with_help_url`].
+    pub fn help_url(self, url: impl Into<String>) -> Self {
+        ErrorBuilder {
+            error: self.error.with_help_url(url),
+        }
+    }
+
+    /// See [`Error::with_section`].
+    pub fn section(self, header: &str, body: String) -> Self {
+        ErrorBuilder {
+            error: self.error.with_section(header, body),
+        }
+    }
+
+    /// See [`Error::suggestion`].
+    pub fn suggestion(self, text: impl Into<String>) -> Self {
+        ErrorBuilder {
+            error: self.error.suggestion(text),
+        }
+    }
+
+    /// The error with everything that was set.
+    pub fn finish(self) -> Error {
+        self.error
+    }
+}
@@ -0,0 +1,146 @@
+use crate::chain::Chain;
+use crate::Error;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+impl Error {
+    /// Render this error as a JSON object for machine consumption.
+    ///
+    /// The object has a `"chain"` array with one `{"message", "source_index"}`
+    /// entry per error in [`chain()`][Error::chain], beginning with this error
+    /// at index 0, and a `"backtrace"` string if a backtrace was captured. The
+    /// backtrace is rendered by [`backtrace_string`][Error::backtrace_string],
+    /// whichever way it was captured.
+    ///
+    /// The [`Serialize`][serde::Serialize] implementation of `Error` gives a
+    /// different shape, with the outermost message at the top level, for
+    /// errors serialized as part of a larger value.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("oh no!").context("f failed");
+    /// assert!(error.to_json().starts_with(
+    ///     r#"{"chain":[{"message":"f failed","source_index":0},{"message":"oh no!","source_index":1}]"#,
+    /// ));
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Json(self))
+            .expect("a Display implementation returned an error unexpectedly")
+    }
+}
+
+/// Serializes the error as an object with the top-level `"message"`, a
+/// `"causes"` array holding the message of each lower level cause, and a
+/// `"backtrace"` string as rendered by
+/// [`backtrace_string`][Error::backtrace_string] if a backtrace was captured.
+///
+/// Every message is the `Display` representation of the corresponding error,
+/// so the underlying error types do not need to implement `Serialize`.
+///
+/// This is not the shape of [`to_json`][Error::to_json]. An error serialized
+/// as part of a larger value, such as the error object of a JSON-RPC
+/// response, reads best with its message at the top level, whereas `to_json`
+/// renders a log record in which every error of the chain is an entry of the
+/// same kind, numbered by its position.
+///
+/// ```
+/// use anyhow::anyhow;
+///
+/// let error = anyhow!("oh no!").context("f failed");
+/// assert!(serde_json::to_string(&error)
+///     .unwrap()
+///     .starts_with(r#"{"message":"f failed","causes":["oh no!"]"#));
+/// ```
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("message", &Message(self.inner.error()))?;
+        map.serialize_entry("causes", &Causes(self.chain().skip(1)))?;
+        if let Some(backtrace) = self.backtrace_string() {
+            map.serialize_entry("backtrace", &backtrace)?;
+        }
+        map.end()
+    }
+}
+
+struct Causes<'a>(core::iter::Skip<Chain<'a>>);
+
+impl Serialize for Causes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let causes = self.0.clone();
+        let mut seq = serializer.serialize_seq(Some(causes.len()))?;
+        for cause in causes {
+            seq.serialize_element(&Message(cause))?;
+        }
+        seq.end()
+    }
+}
+
+struct Json<'a>(&'a Error);
+
+impl Serialize for Json<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("chain", &JsonChain(self.0.chain()))?;
+        if let Some(backtrace) = self.0.backtrace_string() {
+            map.serialize_entry("backtrace", &backtrace)?;
+        }
+        map.end()
+    }
+}
+
+struct JsonChain<'a>(Chain<'a>);
+
+impl Serialize for JsonChain<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let chain = self.0.clone();
+        let mut seq = serializer.serialize_seq(Some(chain.len()))?;
+        for (source_index, error) in chain.enumerate() {
+            seq.serialize_element(&JsonCause {
+                message: error,
+                source_index,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+struct JsonCause<'a> {
+    message: &'a (dyn std::error::Error + 'static),
+    source_index: usize,
+}
+
+impl Serialize for JsonCause<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("message", &Message(self.message))?;
+        map.serialize_entry("source_index", &self.source_index)?;
+        map.end()
+    }
+}
+
+struct Message<'a>(&'a (dyn std::error::Error + 'static));
+
+impl Serialize for Message<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self.0)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::alloc::{Box, String, Vec};
+use crate::{Error, StdError};
+use core::fmt::{self, Display, Write};
+use core::panic::Location;
+
+#[cfg(backtrace)]
+use std::backtrace::Backtrace;
+
+// One error of a snapshot chain, holding only the rendered message and where
+// the original error was created by anyhow! or bail!.
+#[derive(Debug)]
+struct SnapshotError {
+    message: String,
+    #[cfg_attr(not(feature = "track-caller"), allow(dead_code))]
+    creation_location: Option<&'static Location<'static>>,
+    source: Option<Box<SnapshotError>>,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for SnapshotError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.source {
+            Some(source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+// Where the original of `error` was created, if it is an error of a snapshot
+// chain.
+#[cfg_attr(not(feature = "track-caller"), allow(dead_code))]
+pub(crate) fn creation_location(
+    error: &(dyn StdError + 'static),
+) -> Option<&'static Location<'static>> {
+    error.downcast_ref::<SnapshotError>()?.creation_location
+}
+
+// Extension holding the rendered backtrace of the error a snapshot was taken
+// of, since a Backtrace cannot be cloned.
+pub(crate) struct BacktraceSnapshot(pub String);
+
+impl Error {
+    /// Make an owned copy of this error that can be handed out more than once,
+    /// for example to every subscriber waiting on a failed operation.
+    ///
+    /// Every error in the chain is rendered to a string up front, so the
+    /// snapshot displays the same messages in the same order, and carries the
+    /// severity, retryable flag, code and rendered backtrace of the original.
+    /// Its Debug representation is the same as the original's.
+    ///
+    /// The errors in the snapshot are not of the original types, so
+    /// downcasting the snapshot or any of its causes always fails. Other
+    /// extensions attached by [`insert_ext`][Error::insert_ext] are not
+    /// copied.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("connection reset").context("failed to fetch");
+    /// let snapshot = error.clone_snapshot();
+    /// assert_eq!(format!("{:?}", snapshot), format!("{:?}", error));
+    /// ```
+    pub fn clone_snapshot(&self) -> Error {
+        let mut messages = Vec::new();
+        for cause in self.chain() {
+            let mut message = String::new();
+            let _ = write!(message, "{}", cause);
+            messages.push((message, self.inner.creation_location(cause)));
+        }
+
+        let mut snapshot = Error::from_messages(messages);
+        self.copy_metadata(&mut snapshot);
+
+        if let Some(rendered) = self.inner.rendered_backtrace() {
+            snapshot.insert_ext(BacktraceSnapshot(rendered));
+        }
+
+        snapshot
+    }
+
+    /// Rebuild an error from the messages of its chain and its rendered
+    /// backtrace, as returned by [`chain_messages`][Error::chain_messages]
+    /// and [`backtrace_string`][Error::backtrace_string], for example after
+    /// they were sent from a worker process to a coordinator.
+    ///
+    /// The Debug representation of the rebuilt error is the same as the
+    /// original's, apart from metadata such as the severity or where the
+    /// errors were created, which is not part of the messages. Like a
+    /// [snapshot][Error::clone_snapshot], it cannot be downcast to the types
+    /// of the original errors. An empty list of messages gives an error with
+    /// an empty message.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error};
+    ///
+    /// # #[cfg(feature = "track-caller")]
+    /// # anyhow::set_format_config(anyhow::FormatConfig::new().show_creation_location(false));
+    /// let error = anyhow!("connection reset").context("failed to fetch");
+    /// let rebuilt = Error::from_parts(error.chain_messages(), error.backtrace_string());
+    /// assert_eq!(format!("{:?}", rebuilt), format!("{:?}", error));
+    /// ```
+    pub fn from_parts(messages: Vec<String>, backtrace: Option<String>) -> Error {
+        let messages = messages.into_iter().map(|message| (message, None));
+        let mut error = Error::from_messages(messages.collect());
+        if let Some(backtrace) = backtrace {
+            error.insert_ext(BacktraceSnapshot(backtrace));
+        }
+        error
+    }
+
+    // An error whose chain displays as `messages`, from the outermost to the
+    // root cause, each with where its original was created.
+    fn from_messages(mut messages: Vec<(String, Option<&'static Location<'static>>)>) -> Error {
+        if messages.is_empty() {
+            messages.push((String::new(), None));
+        }
+
+        let mut error = None;
+        while let Some((message, creation_location)) = messages.pop() {
+            error = Some(SnapshotError {
+                message,
+                creation_location,
+                source: error.map(Box::new),
+            });
+        }
+
+        #[cfg(backtrace)]
+        let backtrace = Some(Backtrace::disabled());
+        #[cfg(not(backtrace))]
+        let backtrace = None;
+
+        // There is at least one message.
+        Error::from_std(error.unwrap(), backtrace)
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::FormatConfig;
+use std::sync::{Mutex, MutexGuard};
+
+// The format config is process-wide, so tests that depend on it take turns.
+#[allow(clippy::incompatible_msrv)] // the tests need a newer compiler than the library
+static LOCK: Mutex<()> = Mutex::new(());
+
+// The default config, except that backtraces and creation locations are not
+// shown. The tests compare Debug output exactly, which must not depend on
+// whether RUST_BACKTRACE or RUST_LIB_BACKTRACE happen to be set, or on which
+// features are enabled.
+pub fn plain() -> FormatConfig {
+    let config = FormatConfig::new().show_backtrace(false);
+    #[cfg(feature = "track-caller")]
+    let config = config.show_creation_location(false);
+    config
+}
+
+pub struct Guard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+// Format with the given config until the guard is dropped, after which the
+// default config is put back.
+pub fn set(config: FormatConfig) -> Guard {
+    let lock = LOCK.lock().unwrap_or_else(|poison| poison.into_inner());
+    anyhow::set_format_config(config);
+    Guard { _lock: lock }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        anyhow::set_format_config(FormatConfig::new());
+    }
+}
@@ -0,0 +1,124 @@
+mod config;
+
+use anyhow::{anyhow, Error};
+use std::io;
+
+fn aggregate() -> Error {
+    let io_error = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    Error::aggregate(vec![
+        anyhow!("name is empty"),
+        Error::new(io_error).context("failed to read avatar"),
+        anyhow!("age is negative"),
+    ])
+}
+
+#[test]
+fn test_display() {
+    let error = aggregate();
+    assert_eq!("3 errors occurred", error.to_string());
+    assert_eq!("3 errors occurred", format!("{:#}", error));
+}
+
+#[test]
+fn test_debug() {
+    let _config = config::set(config::plain());
+
+    let expected = "\
+3 errors occurred
+
+Errors:
+    0: name is empty
+    1: failed to read avatar
+
+       Caused by:
+           oh no!
+    2: age is negative";
+    assert_eq!(expected, format!("{:?}", aggregate()));
+
+    let error = aggregate().context("invalid form");
+    let expected = "\
+invalid form
+
+Caused by:
+    3 errors occurred
+
+Errors:
+    0: name is empty
+    1: failed to read avatar
+
+       Caused by:
+           oh no!
+    2: age is negative";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_chain() {
+    let error = aggregate();
+    assert_eq!(1, error.chain().count());
+    assert_eq!("3 errors occurred", error.root_cause().to_string());
+
+    let error = error.context("invalid form");
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["invalid form", "3 errors occurred"], *messages);
+}
+
+#[test]
+fn test_members() {
+    let error = aggregate().context("invalid form");
+    let members = error.members().unwrap();
+    assert_eq!(3, members.len());
+    assert_eq!("name is empty", members[0].to_string());
+    let io_error = members[1].downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::NotFound, io_error.kind());
+    assert_eq!("age is negative", members[2].to_string());
+
+    assert!(anyhow!("oh no!").members().is_none());
+}
+
+#[test]
+fn test_empty() {
+    let _config = config::set(config::plain());
+
+    let error = Error::aggregate(Vec::new());
+    assert_eq!("0 errors occurred", format!("{:?}", error));
+    assert!(error.members().unwrap().is_empty());
+}
+
+#[test]
+fn test_collect() {
+    let _config = config::set(config::plain());
+
+    let error: Error = Vec::new().into_iter().collect();
+    assert_eq!("0 errors occurred", format!("{:?}", error));
+    assert!(error.members().unwrap().is_empty());
+
+    let error: Error = vec![anyhow!("oh no!")].into_iter().collect();
+    assert_eq!(
+        "1 error occurred\n\nErrors:\n    0: oh no!",
+        format!("{:?}", error)
+    );
+
+    let results = vec![
+        Err(anyhow!("name is empty")),
+        Ok(1),
+        Err(
+            Error::new(io::Error::new(io::ErrorKind::NotFound, "oh no!"))
+                .context("failed to read avatar"),
+        ),
+        Ok(2),
+        Err(anyhow!("age is negative")),
+    ];
+    let error: Error = results.into_iter().filter_map(Result::err).collect();
+    let expected = "\
+3 errors occurred
+
+Errors:
+    0: name is empty
+    1: failed to read avatar
+
+       Caused by:
+           oh no!
+    2: age is negative";
+    assert_eq!(expected, format!("{:?}", error));
+}
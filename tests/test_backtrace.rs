@@ -11,3 +11,118 @@ fn test_backtrace() {
     let error = anyhow!("oh no!");
     let _ = error.backtrace();
 }
+
+#[cfg(backtrace)]
+#[test]
+fn test_capture_backtrace() {
+    use anyhow::anyhow;
+    use std::backtrace::BacktraceStatus;
+
+    let mut error = anyhow!("oh no!");
+    error.capture_backtrace();
+    assert_eq!(BacktraceStatus::Captured, error.backtrace().status());
+}
+
+#[cfg(backtrace)]
+#[test]
+fn test_unsuccessful_downcast_keeps_backtrace() {
+    use anyhow::anyhow;
+    use std::backtrace::BacktraceStatus;
+
+    let mut error = anyhow!("oh no!").context("context");
+    error.capture_backtrace();
+    let debug = format!("{:?}", error);
+
+    let error = error.downcast::<std::io::Error>().unwrap_err();
+    assert_eq!(BacktraceStatus::Captured, error.backtrace().status());
+    assert_eq!(debug, format!("{:?}", error));
+}
+
+#[test]
+fn test_compact_backtrace() {
+    use std::path::Path;
+
+    let backtrace = "\
+stack backtrace:
+   0: std::backtrace::Backtrace::capture
+             at /rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/backtrace.rs:296:9
+   1: anyhow::error::<impl anyhow::Error>::msg
+             at /home/alice/anyhow/src/error.rs:79:36
+   2: app::load
+             at /home/alice/app/src/load.rs:12:5
+      app::main::{{closure}}
+             at /home/alice/app/src/main.rs:4:17
+   3: 0x55d5c1e2 - app::main
+             at /home/alice/app/src/main.rs:3:5
+   4: 0x7f1c2b3a
+   5: main
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+    let expected = "\
+stack backtrace:
+   0: std::backtrace::Backtrace::capture at library/std/src/backtrace.rs:296
+   1: anyhow::error::<impl anyhow::Error>::msg at /home/alice/anyhow/src/error.rs:79
+   2: app::load at src/load.rs:12
+      app::main::{{closure}} at src/main.rs:4
+   3: app::main at src/main.rs:3
+   4: <unknown>
+   5: main
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+    let root = Path::new("/home/alice/app/");
+    assert_eq!(expected, anyhow::compact_backtrace(backtrace, root));
+}
+
+#[cfg(backtrace)]
+#[test]
+fn test_boundary_captures_backtrace() {
+    use anyhow::{boundary, Result};
+    use std::backtrace::BacktraceStatus;
+    use std::io;
+
+    let result: Result<(), io::Error> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let error = boundary!(result).unwrap_err();
+    assert_eq!(BacktraceStatus::Captured, error.backtrace().status());
+}
+
+#[cfg(not(any(backtrace, backtrace_crate)))]
+#[test]
+fn test_backtrace_string_not_captured() {
+    use anyhow::anyhow;
+
+    let error = anyhow!("oh no!").context("context");
+    assert_eq!(None, error.backtrace_string());
+}
+
+#[cfg(backtrace)]
+#[test]
+fn test_backtrace_string() {
+    use anyhow::anyhow;
+
+    let mut error = anyhow!("oh no!").context("context");
+    error.capture_backtrace();
+    let backtrace = error.backtrace_string().unwrap();
+    assert!(backtrace.starts_with("Stack backtrace:\n"));
+    assert_eq!(backtrace.trim_end(), backtrace);
+    assert!(format!("{:?}", error).ends_with(&backtrace));
+}
+
+#[cfg(backtrace)]
+#[test]
+fn test_set_backtrace() {
+    use anyhow::anyhow;
+    use std::backtrace::{Backtrace, BacktraceStatus};
+
+    let mut error = anyhow!("oh no!").context("context");
+    error.set_backtrace(Some(Backtrace::force_capture()));
+    assert_eq!(BacktraceStatus::Captured, error.backtrace().status());
+    assert!(format!("{:?}", error).contains("\n\nStack backtrace:\n"));
+
+    error.set_backtrace(None);
+    assert_eq!(BacktraceStatus::Disabled, error.backtrace().status());
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error),);
+
+    let mut error = error.ensure_backtrace().clone_snapshot();
+    error.set_backtrace(None);
+    assert_eq!(None, error.backtrace_string());
+}
@@ -0,0 +1,73 @@
+#![cfg(all(feature = "backtrace-crate", not(feature = "wasm")))]
+
+use anyhow::{anyhow, Context, Error, FieldVisitor};
+use std::fmt::Display;
+use std::io;
+
+#[inline(never)]
+fn make_error() -> Error {
+    anyhow!("oh no!")
+}
+
+// A single test, since whether backtraces are captured is decided once per
+// process from the environment.
+#[test]
+fn test_backtrace_crate() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = make_error();
+    let debug = format!("{:?}", error);
+    let (message, backtrace) = debug.split_once("\n\nStack backtrace:\n").unwrap();
+    assert_eq!("oh no!", message);
+    assert!(backtrace.lines().count() > 1);
+    assert!(backtrace.contains("make_error"));
+    assert!(backtrace.contains("test_backtrace_crate.rs:"));
+
+    let rendered = error.backtrace_string().unwrap();
+    assert!(rendered.starts_with("Stack backtrace:\n"));
+    assert_eq!(rendered.trim_end(), rendered);
+    assert!(debug.ends_with(&rendered));
+
+    struct Names(Vec<String>);
+    impl FieldVisitor for Names {
+        fn record(&mut self, name: &str, _value: &dyn Display) {
+            self.0.push(name.to_owned());
+        }
+    }
+    let mut names = Names(Vec::new());
+    error.record_fields(&mut names);
+    assert_eq!(["error.message", "error.backtrace"], *names.0);
+
+    // The backtrace is kept when context is added.
+    let error = error.context("context");
+    let debug = format!("{:?}", error);
+    assert!(debug.starts_with("context\n\nCaused by:\n    oh no!\n\nStack backtrace:\n"));
+    assert!(debug.contains("make_error"));
+
+    // Also when it is added in place.
+    let mut error = make_error();
+    let rendered = error.backtrace_string().unwrap();
+    error.push_context("context");
+    assert_eq!(Some(rendered), error.backtrace_string());
+
+    // And captured for errors that do not come from anyhow.
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let error = result.context("context").unwrap_err();
+    assert!(format!("{:?}", error).contains("\n\nStack backtrace:\n"));
+    assert_eq!(
+        format!("{:?}", error),
+        format!("{:?}", error.clone_snapshot())
+    );
+
+    // Removing the context keeps the backtrace, whichever layer holds it.
+    let rendered = error.backtrace_string().unwrap();
+    let error = error.context("outer").into_inner();
+    assert!(error.is::<io::Error>());
+    assert_eq!(Some(rendered), error.backtrace_string());
+
+    let error = make_error();
+    let rendered = error.backtrace_string().unwrap();
+    let error = error.context("inner").context("outer").into_inner();
+    assert_eq!("oh no!", format!("{:?}", error).lines().next().unwrap());
+    assert_eq!(Some(rendered), error.backtrace_string());
+}
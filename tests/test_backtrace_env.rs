@@ -0,0 +1,53 @@
+use anyhow::{Error, FormatConfig};
+use std::env;
+
+const BACKTRACE: &str = "Stack backtrace:\n   0: app::main\n             at src/main.rs:4";
+
+fn debug() -> String {
+    let error = Error::from_parts(vec!["oh no!".to_owned()], Some(BACKTRACE.to_owned()));
+    assert_eq!(Some(BACKTRACE.to_owned()), error.backtrace_string());
+    format!("{:?}", error)
+}
+
+fn debug_with(rust_lib_backtrace: Option<&str>, rust_backtrace: Option<&str>) -> String {
+    anyhow::set_format_config(
+        FormatConfig::new().backtrace_env(rust_lib_backtrace, rust_backtrace),
+    );
+    debug()
+}
+
+// A single test, since the environment and the format config are shared by
+// the whole process.
+#[test]
+fn test_backtrace_env() {
+    let shown = format!("oh no!\n\n{}", BACKTRACE);
+    let hidden = "oh no!";
+
+    // The environment of the process is read once.
+    env::set_var("RUST_LIB_BACKTRACE", "0");
+    env::set_var("RUST_BACKTRACE", "1");
+    assert_eq!(hidden, debug());
+    env::set_var("RUST_LIB_BACKTRACE", "1");
+    assert_eq!(hidden, debug());
+
+    assert_eq!(shown, debug_with(None, None));
+    assert_eq!(hidden, debug_with(None, Some("0")));
+    assert_eq!(shown, debug_with(None, Some("1")));
+    assert_eq!(shown, debug_with(None, Some("full")));
+
+    // RUST_LIB_BACKTRACE takes precedence either way.
+    assert_eq!(hidden, debug_with(Some("0"), None));
+    assert_eq!(hidden, debug_with(Some("0"), Some("1")));
+    assert_eq!(shown, debug_with(Some("1"), Some("0")));
+
+    // Whichever of the two settings comes last wins.
+    let config = FormatConfig::new().show_backtrace(false);
+    anyhow::set_format_config(config.backtrace_env(None, None));
+    assert_eq!(shown, debug());
+    let config = FormatConfig::new().backtrace_env(None, None);
+    anyhow::set_format_config(config.show_backtrace(false));
+    assert_eq!(hidden, debug());
+
+    anyhow::set_format_config(FormatConfig::new());
+    assert_eq!(hidden, debug());
+}
@@ -0,0 +1,33 @@
+#![cfg(all(feature = "backtrace-crate", not(feature = "wasm")))]
+
+use anyhow::{anyhow, Error};
+
+#[inline(never)]
+fn make_error() -> Error {
+    anyhow!("oh no!")
+}
+
+// A single test, since the filter is global to the process.
+#[test]
+fn test_backtrace_filter() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = make_error();
+    let everything = error.backtrace_string().unwrap();
+    assert!(everything.contains("make_error"));
+
+    anyhow::set_backtrace_filter(Box::new(|frame| {
+        !frame.symbols().iter().any(|symbol| match symbol.name() {
+            Some(name) => name.to_string().contains("make_error"),
+            None => false,
+        })
+    }));
+    let filtered = error.backtrace_string().unwrap();
+    assert!(!filtered.contains("make_error"));
+    assert!(filtered.lines().count() < everything.lines().count());
+    assert!(filtered.starts_with("Stack backtrace:\n   0: "));
+    assert!(!format!("{:?}", error).contains("make_error"));
+
+    anyhow::set_backtrace_filter(Box::new(|_frame| true));
+    assert_eq!(Some(everything), error.backtrace_string());
+}
@@ -1,4 +1,6 @@
-use anyhow::anyhow;
+mod config;
+
+use anyhow::{anyhow, Error as AnyError};
 use std::error::Error as StdError;
 use std::io;
 use thiserror::Error;
@@ -38,3 +40,25 @@ fn test_boxed_anyhow() {
     let error = anyhow!(error);
     assert_eq!("oh no!", error.source().unwrap().to_string());
 }
+
+#[test]
+fn test_from_boxed() {
+    let _config = config::set(config::plain());
+
+    let error = MyError {
+        source: io::Error::new(io::ErrorKind::Other, "oh no!"),
+    };
+    let boxed: Box<dyn StdError + Send + Sync> = Box::new(error);
+    let error = AnyError::from_boxed(boxed).context("context");
+
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["context", "outer", "oh no!"], *chain);
+    assert_eq!(
+        "context\n\nCaused by:\n    0: outer\n    1: oh no!",
+        format!("{:?}", error),
+    );
+
+    let boxed = error.downcast::<Box<dyn StdError + Send + Sync>>().unwrap();
+    let error = boxed.downcast::<MyError>().unwrap();
+    assert_eq!(io::ErrorKind::Other, error.source.kind());
+}
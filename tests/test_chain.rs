@@ -1,4 +1,11 @@
-use anyhow::{anyhow, Error};
+mod config;
+
+use anyhow::{anyhow, Context as _, Error};
+use std::any::TypeId;
+use std::fmt::{self, Display};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error;
 
 fn error() -> Error {
     anyhow!(0).context(1).context(2).context(3)
@@ -43,3 +50,369 @@ fn test_len() {
     assert_eq!(0, chain.len());
     assert!(chain.next().is_none());
 }
+
+#[test]
+fn test_size_hint() {
+    let e = error();
+    let mut chain = e.chain();
+    assert_eq!((4, Some(4)), chain.size_hint());
+    chain.next();
+    assert_eq!((3, Some(3)), chain.size_hint());
+    chain.next_back();
+    assert_eq!((2, Some(2)), chain.size_hint());
+}
+
+#[test]
+fn test_chain_len() {
+    assert_eq!(1, anyhow!(0).chain_len());
+    assert_eq!(4, error().chain_len());
+
+    let io_error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    assert_eq!(2, Error::new(io_error).context("context").chain_len());
+}
+
+#[test]
+fn test_matches_debug() {
+    let _config = config::set(config::plain());
+
+    let e = error();
+
+    let mut expected = String::new();
+    for (n, cause) in e.chain().enumerate() {
+        match n {
+            0 => expected += &format!("{}\n\nCaused by:", cause),
+            _ => expected += &format!("\n    {}: {}", n - 1, cause),
+        }
+    }
+    assert_eq!(expected, format!("{:?}", e));
+
+    let mut expected = String::new();
+    for (n, cause) in e.chain().rev().enumerate() {
+        match n {
+            0 => expected += &format!("{}\n\nWhich caused:", cause),
+            _ => expected += &format!("\n    {}: {}", n - 1, cause),
+        }
+    }
+    assert_eq!(expected, format!("{:+?}", e));
+}
+
+#[test]
+fn test_eq_message() {
+    assert!(error().eq_message(&error()));
+
+    let io_error = io::Error::new(io::ErrorKind::Other, "0");
+    let same = Error::new(io_error).context(1).context("2").context(3);
+    assert!(error().eq_message(&same));
+
+    let shallower = anyhow!(1).context(2).context(3);
+    assert!(!error().eq_message(&shallower));
+    assert!(!shallower.eq_message(&error()));
+
+    let different = anyhow!(0).context(1).context(9).context(3);
+    assert!(!error().eq_message(&different));
+}
+
+#[test]
+fn test_source_at() {
+    let e = error();
+    assert_eq!("3", e.source_at(0).unwrap().to_string());
+    assert_eq!("1", e.source_at(2).unwrap().to_string());
+    assert_eq!("0", e.source_at(3).unwrap().to_string());
+    assert!(e.source_at(4).is_none());
+    assert!(e.source_at(usize::MAX).is_none());
+}
+
+// Errors whose source() goes around a ring of statics forever.
+#[derive(Copy, Clone, Debug)]
+struct Ring {
+    index: usize,
+    ring: &'static [Ring],
+}
+
+impl std::fmt::Display for Ring {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.index)
+    }
+}
+
+impl std::error::Error for Ring {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.ring[(self.index + 1) % self.ring.len()])
+    }
+}
+
+static SHORT: [Ring; 2] = [
+    Ring {
+        index: 0,
+        ring: &SHORT,
+    },
+    Ring {
+        index: 1,
+        ring: &SHORT,
+    },
+];
+
+#[allow(clippy::incompatible_msrv)] // the tests need a newer compiler than the library
+static LONG: [Ring; 100] = {
+    let mut ring = [Ring {
+        index: 0,
+        ring: &LONG,
+    }; 100];
+    let mut i = 0;
+    while i < ring.len() {
+        ring[i].index = i;
+        i += 1;
+    }
+    ring
+};
+
+#[test]
+fn test_cycle() {
+    let _config = config::set(config::plain());
+
+    // The error holds a copy of SHORT[0], so the chain goes once around the
+    // ring after it.
+    let e = Error::new(SHORT[0]).context("context");
+    let messages: Vec<_> = e.chain().map(ToString::to_string).collect();
+    assert_eq!(["context", "0", "1", "0"], *messages);
+    assert_eq!(4, e.chain().len());
+    assert_eq!(4, e.chain().rev().count());
+
+    let expected = "\
+context
+
+Caused by:
+    0: 0
+    1: 1
+    2: 0
+    (cycle detected)";
+    assert_eq!(expected, format!("{:?}", e));
+    assert_eq!("context: 0: 1: 0", format!("{:#}", e));
+}
+
+#[test]
+fn test_self_cycle() {
+    let _config = config::set(config::plain());
+
+    #[derive(Debug)]
+    struct SelfCycle;
+
+    impl std::fmt::Display for SelfCycle {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("self")
+        }
+    }
+
+    impl std::error::Error for SelfCycle {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(self)
+        }
+    }
+
+    // The error may be yielded a second time, if the reference to it inside
+    // anyhow and the one returned by source() got different vtables.
+    let e = Error::new(SelfCycle);
+    let count = e.chain().count();
+    assert!(count == 1 || count == 2);
+    assert_eq!(count, e.chain().len());
+    let debug = format!("{:?}", e);
+    assert!(debug.starts_with("self\n\nCaused by:\n"));
+    assert!(debug.ends_with("\n    (cycle detected)"));
+}
+
+#[test]
+fn test_deep_cycle() {
+    let _config = config::set(config::plain());
+
+    let e = Error::new(LONG[0]);
+    assert_eq!(101, e.chain().len());
+    assert_eq!(101, e.chain().count());
+    assert_eq!("0", e.chain().last().unwrap().to_string());
+    assert!(format!("{:?}", e).ends_with("\n    98: 99\n    99: 0\n    (cycle detected)"));
+}
+
+#[test]
+fn test_deep_chain_walked_once() {
+    static SOURCE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Link(Option<Box<Link>>);
+
+    impl Display for Link {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("link")
+        }
+    }
+
+    impl std::error::Error for Link {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            SOURCE_CALLS.fetch_add(1, Ordering::Relaxed);
+            self.0.as_deref().map(|next| next as _)
+        }
+    }
+
+    let mut link = Link(None);
+    for _ in 1..1000 {
+        link = Link(Some(Box::new(link)));
+    }
+    let e = Error::new(link);
+
+    // Each error is asked for its source once, rather than the chain being
+    // walked again from the head to look for cycles.
+    assert_eq!(1000, e.chain().count());
+    assert_eq!(1000, SOURCE_CALLS.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_chain_messages() {
+    let e = error();
+    let expected: Vec<String> = e.chain().map(ToString::to_string).collect();
+    assert_eq!(expected, e.chain_messages());
+    assert_eq!(["3", "2", "1", "0"], *e.chain_messages());
+
+    let io_error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let e = Error::new(io_error).context("context");
+    assert_eq!(["context", "oh no!"], *e.chain_messages());
+}
+
+#[test]
+fn test_display() {
+    let e = error();
+    assert_eq!(format!("{:#}", e), e.chain().to_string());
+    assert_eq!("3: 2: 1: 0", e.chain().to_string());
+
+    let mut chain = e.chain();
+    chain.next();
+    assert_eq!("2: 1: 0", chain.to_string());
+    chain.next_back();
+    assert_eq!("2: 1", chain.to_string());
+}
+
+#[test]
+fn test_sources() {
+    let e = error();
+    let sources: Vec<_> = e.chain().sources().map(ToString::to_string).collect();
+    let expected: Vec<_> = e.chain().skip(1).map(ToString::to_string).collect();
+    assert_eq!(expected, sources);
+    assert_eq!(["2", "1", "0"], *sources);
+    assert_eq!(3, e.chain().sources().len());
+    assert_eq!("0", e.chain().sources().next_back().unwrap().to_string());
+
+    assert!(anyhow!(0).chain().sources().next().is_none());
+}
+
+#[test]
+fn test_chain_typed() {
+    #[derive(Error, Debug)]
+    #[error("outer")]
+    struct Outer {
+        #[source]
+        source: io::Error,
+    }
+
+    #[derive(Debug)]
+    struct Context;
+
+    impl Display for Context {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("context")
+        }
+    }
+
+    let outer = Outer {
+        source: io::Error::new(io::ErrorKind::Other, "oh no!"),
+    };
+    let error = Err::<(), _>(outer)
+        .context(Context)
+        .unwrap_err()
+        .context("top")
+        .context(String::from("string"));
+
+    let types: Vec<_> = error
+        .chain_typed()
+        .map(|(error, type_id)| (error.to_string(), type_id))
+        .collect();
+    assert_eq!(
+        [
+            ("string".to_owned(), Some(TypeId::of::<String>())),
+            ("top".to_owned(), Some(TypeId::of::<&str>())),
+            ("context".to_owned(), Some(TypeId::of::<Context>())),
+            ("outer".to_owned(), Some(TypeId::of::<Outer>())),
+            ("oh no!".to_owned(), None),
+        ],
+        *types,
+    );
+
+    let error = anyhow!(0);
+    let (_, type_id) = error.chain_typed().next().unwrap();
+    assert_eq!(Some(TypeId::of::<i32>()), type_id);
+
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let (_, type_id) = error.chain_typed().next().unwrap();
+    assert_eq!(Some(TypeId::of::<io::Error>()), type_id);
+}
+
+#[test]
+fn test_walk() {
+    let e = error();
+    let mut visited = Vec::new();
+    e.walk(|depth, error| visited.push((depth, error.to_string())));
+
+    let expected: Vec<_> = e.chain().map(ToString::to_string).enumerate().collect();
+    assert_eq!(expected, visited);
+    assert_eq!((0, "3".to_owned()), visited[0]);
+    assert_eq!((3, "0".to_owned()), visited[3]);
+}
+
+#[test]
+fn test_filter_type() {
+    #[derive(Error, Debug)]
+    #[error("{message}")]
+    struct Message {
+        message: &'static str,
+        #[source]
+        source: io::Error,
+    }
+
+    // The source of an io::Error is the source of its payload, so this puts
+    // the Message between the two io::Errors of the chain.
+    #[derive(Error, Debug)]
+    #[error("connection reset")]
+    struct Reset(#[source] Message);
+
+    let root = io::Error::new(io::ErrorKind::NotFound, "no route to host");
+    let retry = Message {
+        message: "retry failed",
+        source: root,
+    };
+    let reset = io::Error::new(io::ErrorKind::ConnectionReset, Reset(retry));
+    let error = Error::new(reset).context("sync failed");
+
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    let expected = [
+        "sync failed",
+        "connection reset",
+        "retry failed",
+        "no route to host",
+    ];
+    assert_eq!(expected, *messages);
+
+    let kinds: Vec<_> = error
+        .chain()
+        .filter_type::<io::Error>()
+        .map(io::Error::kind)
+        .collect();
+    assert_eq!(
+        [io::ErrorKind::ConnectionReset, io::ErrorKind::NotFound],
+        *kinds,
+    );
+
+    let messages: Vec<_> = error
+        .chain()
+        .filter_type::<Message>()
+        .map(|message| message.message)
+        .collect();
+    assert_eq!(["retry failed"], *messages);
+
+    assert_eq!(0, error.chain().filter_type::<fmt::Error>().count());
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "color")]
+
+mod config;
+
+use anyhow::{anyhow, Error};
+use std::env;
+
+#[test]
+fn test_debug_colored() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("oh no!").context("f failed").context("g failed");
+
+    // Colors are off unless asked for.
+    let expected = "\
+g failed
+
+Caused by:
+    0: f failed
+    1: oh no!";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let expected = "\
+\x1b[1mg failed\x1b[0m
+
+Caused by:
+    \x1b[2m0:\x1b[0m f failed
+    \x1b[2m1:\x1b[0m oh no!";
+    assert_eq!(expected, format!("{:?}", error.debug_colored()));
+
+    anyhow::set_color(true);
+    assert_eq!(expected, format!("{:?}", error));
+    anyhow::set_color(false);
+}
+
+#[test]
+fn test_help_url_hyperlink() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("invalid manifest").with_help_url("https://docs.example/E1");
+    let expected = "\
+\x1b[1minvalid manifest\x1b[0m
+
+See: \x1b]8;;https://docs.example/E1\x1b\\https://docs.example/E1\x1b]8;;\x1b\\";
+    assert_eq!(expected, format!("{:?}", error.debug_colored()));
+    assert_eq!(
+        "invalid manifest\n\nSee: https://docs.example/E1",
+        error.report(),
+    );
+}
+
+#[test]
+fn test_backtrace_hyperlinks() {
+    let _config = config::set(config::plain().show_backtrace(true));
+
+    let backtrace = "\
+Stack backtrace:
+   0: app::main
+             at /home/alice/app/src/main.rs:12:5
+   1: app::run at src/run.rs:7
+   2: <unknown>";
+    let error = Error::from_parts(vec!["oh no!".to_owned()], Some(backtrace.to_owned()));
+
+    let relative = env::current_dir().unwrap().join("src/run.rs");
+    let expected = format!(
+        "\
+\x1b[1moh no!\x1b[0m
+
+\x1b[1;35mStack backtrace:\x1b[0m
+   0: app::main
+             at \x1b]8;;file:///home/alice/app/src/main.rs\x1b\\/home/alice/app/src/main.rs\x1b]8;;\x1b\\:12:5
+   1: app::run at \x1b]8;;file://{}\x1b\\src/run.rs\x1b]8;;\x1b\\:7
+   2: <unknown>",
+        relative.display(),
+    );
+    assert_eq!(expected, format!("{:?}", error.debug_colored()));
+
+    assert_eq!(format!("oh no!\n\n{}", backtrace), error.report());
+}
@@ -0,0 +1,367 @@
+mod config;
+
+use anyhow::{anyhow, Error, GutterStyle};
+
+fn error() -> Error {
+    anyhow!("root").context("middle").context("outer")
+}
+
+#[test]
+fn test_default_indent() {
+    let _config = config::set(config::plain());
+
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+}
+
+#[test]
+fn test_custom_indent() {
+    let _config = config::set(config::plain().indent(2));
+
+    let expected = "outer\n\nCaused by:\n  0: middle\n  1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+
+    anyhow::set_format_config(config::plain().indent(8));
+    let expected = "outer\n\nCaused by:\n        0: middle\n        1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+}
+
+// By default the lines after the first in a cause are written as they are,
+// the same as before the layout became configurable.
+#[test]
+fn test_default_multiline_cause() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("first line\n\nsecond line").context("outer");
+    let expected = "outer\n\nCaused by:\n    first line\n\nsecond line";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = error.context("more");
+    let expected = "more\n\nCaused by:\n    0: outer\n    1: first line\n\nsecond line";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_multiline_cause() {
+    let _config = config::set(config::plain().indent(2).align_continuation(true));
+
+    let error = anyhow!("first line\n\nsecond line").context("outer");
+    let expected = "outer\n\nCaused by:\n  first line\n\n  second line";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = error.context("more");
+    let expected = "more\n\nCaused by:\n  0: outer\n  1: first line\n\n     second line";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_two_digit_index() {
+    let _config = config::set(config::plain().align_continuation(true));
+
+    let mut error = anyhow!("a\nb");
+    for i in 0..11 {
+        error = error.context(i);
+    }
+    let debug = format!("{:?}", error);
+    assert!(
+        debug.ends_with("\n    9: 0\n    10: a\n        b"),
+        "{}",
+        debug
+    );
+}
+
+#[test]
+fn test_wrap() {
+    let _config = config::set(config::plain().wrap(24));
+
+    let error = anyhow!("the quick brown fox jumps over the lazy dog").context("outer");
+    let expected = "\
+outer
+
+Caused by:
+    the quick brown fox
+    jumps over the lazy
+    dog";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = anyhow!("a message that is too long\nfor one line")
+        .context("middle")
+        .context("outer");
+    let expected = "\
+outer
+
+Caused by:
+    0: middle
+    1: a message that is
+       too long
+       for one line";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_wrap_escapes() {
+    let _config = config::set(config::plain().wrap(16));
+
+    let error = anyhow!("\x1b[1mbold\x1b[0m words here").context("outer");
+    let expected = "outer\n\nCaused by:\n    \x1b[1mbold\x1b[0m words\n    here";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = anyhow!("unbreakable_long_word_here").context("outer");
+    let expected = "outer\n\nCaused by:\n    unbreakable_long_word_here";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_dedup() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("root")
+        .context("failed")
+        .context("failed")
+        .context("outer")
+        .context("failed");
+
+    anyhow::set_format_config(config::plain());
+    let expected = "failed\n\nCaused by:\n    0: outer\n    1: failed\n    2: failed\n    3: root";
+    assert_eq!(expected, format!("{:?}", error));
+
+    anyhow::set_format_config(config::plain().dedup(true));
+    let expected = "failed\n\nCaused by:\n    0: outer\n    1: failed (x2)\n    2: root";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = anyhow!("root").context("root").context("outer");
+    let expected = "outer\n\nCaused by:\n    root (x2)";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_dedup_all() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("root")
+        .context("request failed")
+        .context("middle")
+        .context("request failed")
+        .context("outer")
+        .context("request failed");
+
+    anyhow::set_format_config(config::plain().dedup_all(true));
+    let expected = "request failed\n\nCaused by:\n    0: outer\n    1: middle\n    2: root";
+    assert_eq!(expected, format!("{:?}", error));
+
+    // The first occurrence in the printed order is kept.
+    let expected = "root\n\nWhich caused:\n    0: request failed\n    1: middle\n    2: outer";
+    assert_eq!(expected, format!("{:+?}", error));
+
+    // Repeats that directly follow the first occurrence are still counted
+    // by adjacent dedup.
+    let error = anyhow!("root")
+        .context("failed")
+        .context("outer")
+        .context("failed")
+        .context("failed");
+    anyhow::set_format_config(config::plain().dedup(true).dedup_all(true));
+    let expected = "failed (x2)\n\nCaused by:\n    0: outer\n    1: root";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_max_causes() {
+    let _config = config::set(config::plain());
+
+    let mut error = anyhow!("root");
+    for i in 0..100 {
+        error = error.context(i);
+    }
+
+    anyhow::set_format_config(config::plain().max_causes(5));
+    let expected = "\
+99
+
+Caused by:
+    0: 98
+    1: 97
+    2: 96
+    3: 95
+    4: 94
+    ... (95 more causes)";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let expected = "\
+root
+
+Which caused:
+    0: 0
+    1: 1
+    2: 2
+    3: 3
+    4: 4
+    ... (95 more causes)";
+    assert_eq!(expected, format!("{:+?}", error));
+
+    let error = anyhow!("root").context("middle").context("outer");
+    anyhow::set_format_config(config::plain().max_causes(1));
+    let expected = "outer\n\nCaused by:\n    0: middle\n    ... (1 more cause)";
+    assert_eq!(expected, format!("{:?}", error));
+
+    anyhow::set_format_config(config::plain().max_causes(2));
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_headers() {
+    let _config = config::set(
+        config::plain()
+            .caused_by_header("Verursacht durch:")
+            .which_caused_header(String::from("Was verursachte:"))
+            .backtrace_header("Backtrace:"),
+    );
+
+    let expected = "outer\n\nVerursacht durch:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+    let expected = "root\n\nWas verursachte:\n    0: middle\n    1: outer";
+    assert_eq!(expected, format!("{:+?}", error()));
+
+    anyhow::set_format_config(config::plain());
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+    let expected = "root\n\nWhich caused:\n    0: middle\n    1: outer";
+    assert_eq!(expected, format!("{:+?}", error()));
+}
+
+#[test]
+fn test_blank_lines() {
+    let _config = config::set(config::plain().blank_lines(false));
+
+    let expected = "outer\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+    let expected = "root\nWhich caused:\n    0: middle\n    1: outer";
+    assert_eq!(expected, format!("{:+?}", error()));
+    let sectioned = error().with_section("Note:", "retrying".to_owned());
+    let expected = "outer\nCaused by:\n    0: middle\n    1: root\nNote:\n    retrying";
+    assert_eq!(expected, format!("{:?}", sectioned));
+
+    anyhow::set_format_config(config::plain().blank_lines(true));
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+    let expected = "root\n\nWhich caused:\n    0: middle\n    1: outer";
+    assert_eq!(expected, format!("{:+?}", error()));
+}
+
+#[test]
+fn test_no_leading_newline() {
+    let _config = config::set(config::plain());
+
+    for blank_lines in [true, false] {
+        anyhow::set_format_config(config::plain().blank_lines(blank_lines));
+        assert!(format!("{:?}", error()).starts_with("outer\n"));
+        assert!(format!("{:+?}", error()).starts_with("root\n"));
+    }
+}
+
+#[test]
+fn test_truncate() {
+    let _config = config::set(config::plain());
+
+    let body = "ü".repeat(100);
+    let fetch = anyhow!("{}", body)
+        .context("fetch failed: 🦀🦀🦀")
+        .with_section("Body:", body.clone());
+
+    anyhow::set_format_config(config::plain().truncate(15));
+    let expected = format!(
+        "fetch failed: 🦀…\n\nCaused by:\n    üüüüüüüüüüüüüüü…\n\nBody:\n    {}",
+        body,
+    );
+    assert_eq!(expected, format!("{:?}", fetch));
+
+    anyhow::set_format_config(config::plain().truncate(16));
+    let expected = "fetch failed: 🦀🦀…\n\nCaused by:\n    üüüüüüüüüüüüüüüü…";
+    assert!(format!("{:?}", fetch).starts_with(expected));
+
+    // Messages no longer than the limit are kept as they are.
+    anyhow::set_format_config(config::plain().truncate(6));
+    assert_eq!(
+        "outer\n\nCaused by:\n    0: middle\n    1: root",
+        format!("{:?}", error())
+    );
+
+    // Errors are deduplicated by their whole message.
+    anyhow::set_format_config(config::plain().truncate(4).dedup(true));
+    let dedup = anyhow!("abcdX").context("abcdY").context("abcdY");
+    assert_eq!(
+        "abcd… (x2)\n\nCaused by:\n    abcd…",
+        format!("{:?}", dedup)
+    );
+}
+
+#[test]
+fn test_gutter() {
+    let _config = config::set(config::plain());
+
+    let error = || {
+        anyhow!("first line\nsecond line")
+            .context("middle")
+            .context("outer")
+    };
+
+    anyhow::set_format_config(
+        config::plain()
+            .gutter(GutterStyle::Numbered)
+            .align_continuation(true),
+    );
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: first line\n       second line";
+    assert_eq!(expected, format!("{:?}", error()));
+
+    anyhow::set_format_config(
+        config::plain()
+            .gutter(GutterStyle::Bullet)
+            .align_continuation(true),
+    );
+    let expected = "outer\n\nCaused by:\n    • middle\n    • first line\n      second line";
+    assert_eq!(expected, format!("{:?}", error()));
+
+    anyhow::set_format_config(
+        config::plain()
+            .gutter(GutterStyle::Arrow)
+            .align_continuation(true),
+    );
+    let expected = "outer\n\nCaused by:\n    ↳ middle\n    ↳ first line\n      second line";
+    assert_eq!(expected, format!("{:?}", error()));
+    let expected = "first line\nsecond line\n\nWhich caused:\n    ↳ middle\n    ↳ outer";
+    assert_eq!(expected, format!("{:+?}", error()));
+
+    // A single cause has no marker in any style.
+    let single = anyhow!("root").context("outer");
+    assert_eq!("outer\n\nCaused by:\n    root", format!("{:?}", single));
+}
+
+#[test]
+fn test_number_threshold() {
+    let _config = config::set(config::plain());
+
+    let single = || anyhow!("root").context("outer");
+
+    anyhow::set_format_config(config::plain().number_threshold(1));
+    assert_eq!(
+        "outer\n\nCaused by:\n    0: root",
+        format!("{:?}", single())
+    );
+    assert_eq!(
+        "root\n\nWhich caused:\n    0: outer",
+        format!("{:+?}", single())
+    );
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+    assert_eq!("root", format!("{:?}", anyhow!("root")));
+
+    anyhow::set_format_config(config::plain().number_threshold(3));
+    let expected = "outer\n\nCaused by:\n    middle\n    root";
+    assert_eq!(expected, format!("{:?}", error()));
+
+    anyhow::set_format_config(config::plain());
+    assert_eq!("outer\n\nCaused by:\n    root", format!("{:?}", single()));
+    let expected = "outer\n\nCaused by:\n    0: middle\n    1: root";
+    assert_eq!(expected, format!("{:?}", error()));
+}
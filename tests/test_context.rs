@@ -1,8 +1,11 @@
+mod config;
 mod drop;
 
 use crate::drop::{DetectDrop, Flag};
 use anyhow::{Context, Error, Result};
+use std::cell::Cell;
 use std::fmt::{self, Display};
+use std::io;
 use thiserror::Error;
 
 // https://github.com/dtolnay/anyhow/issues/18
@@ -19,6 +22,7 @@ macro_rules! context_type {
         #[derive(Debug)]
         struct $name {
             message: &'static str,
+            #[allow(dead_code)]
             drop: DetectDrop,
         }
 
@@ -157,3 +161,449 @@ fn test_unsuccessful_downcast() {
     drop(err);
     assert!(dropped.all());
 }
+
+#[test]
+fn test_context_messages() {
+    let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "oh no!");
+    let error = Err::<(), _>(io_error)
+        .context("failed to read")
+        .context(2)
+        .unwrap_err();
+    assert_eq!(error.context_messages(), ["2", "failed to read"]);
+    assert_eq!(3, error.chain().count());
+
+    let error = anyhow::anyhow!("oh no!").context("failed to read");
+    assert_eq!(error.context_messages(), ["failed to read"]);
+
+    let error = anyhow::anyhow!("oh no!");
+    assert!(error.context_messages().is_empty());
+}
+
+#[test]
+fn test_context_macro() {
+    use anyhow::context;
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let count = || {
+        calls.set(calls.get() + 1);
+        "path"
+    };
+
+    let ok: Result<i32, std::io::Error> = Ok(1);
+    assert_eq!(1, context!(ok, "processing {}", count()).unwrap());
+    assert_eq!(0, calls.get());
+
+    let err = Err::<(), _>(std::io::Error::new(std::io::ErrorKind::Other, "oh no!"));
+    let error = context!(err, "processing {}", count()).unwrap_err();
+    assert_eq!(1, calls.get());
+    assert_eq!("processing path: oh no!", format!("{:#}", error));
+
+    let error = context!(Err::<(), _>(error), "literal").unwrap_err();
+    assert_eq!("literal", error.to_string());
+
+    let error = context!(Err::<(), _>(error), count().len()).unwrap_err();
+    assert_eq!("4", error.to_string());
+    assert_eq!(2, calls.get());
+
+    let none: Option<i32> = None;
+    let error = context!(none, "missing {}", "value").unwrap_err();
+    assert_eq!("missing value", error.to_string());
+}
+
+#[test]
+fn test_context_at() {
+    let _config = config::set(config::plain());
+
+    use anyhow::{anyhow, context_at};
+
+    let result: Result<(), _> = Err(anyhow!("oh no!"));
+    let line = line!() + 1;
+    let error = context_at!(result, "failed to {}", "frobnicate").unwrap_err();
+    let expected = format!(
+        "failed to frobnicate at tests/test_context.rs:{}\n\nCaused by:\n    oh no!",
+        line,
+    );
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!("failed to frobnicate", error.to_string());
+
+    let io_error = std::io::Error::new(std::io::ErrorKind::Other, "oh no!");
+    let result: Result<(), _> = Err(io_error);
+    let line = line!() + 1;
+    let error = context_at!(result, "inner").context("outer");
+    let expected = format!(
+        "outer\n\nCaused by:\n    0: inner at tests/test_context.rs:{}\n    1: oh no!",
+        line,
+    );
+    assert_eq!(expected, format!("{:?}", error.unwrap_err()));
+}
+
+#[test]
+fn test_with_context_err() {
+    let _config = config::set(config::plain());
+
+    #[derive(Error, Debug)]
+    #[error("failed to load {path}")]
+    struct LoadError {
+        path: &'static str,
+    }
+
+    let calls = std::cell::Cell::new(0);
+    let context = || {
+        calls.set(calls.get() + 1);
+        LoadError {
+            path: "config.toml",
+        }
+    };
+
+    let ok: Result<i32, std::io::Error> = Ok(1);
+    assert_eq!(1, ok.with_context_err(context).unwrap());
+    assert_eq!(0, calls.get());
+
+    let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "oh no!");
+    let error = Err::<(), _>(io_error)
+        .with_context_err(context)
+        .context("outer")
+        .unwrap_err();
+    assert_eq!(1, calls.get());
+    assert_eq!(
+        "config.toml",
+        error.downcast_ref::<LoadError>().unwrap().path
+    );
+
+    let layer = error.chain().nth(1).unwrap();
+    assert_eq!("failed to load config.toml", layer.to_string());
+    let source = layer.source().unwrap();
+    assert_eq!("oh no!", source.to_string());
+    assert!(source.is::<std::io::Error>());
+    assert_eq!(
+        "outer\n\nCaused by:\n    0: failed to load config.toml\n    1: oh no!",
+        format!("{:?}", error),
+    );
+
+    let error = None::<()>.with_context_err(context).unwrap_err();
+    assert!(error.is::<LoadError>());
+}
+
+#[test]
+fn test_with_context_err_own_source() {
+    #[derive(Error, Debug)]
+    #[error("failed to load config")]
+    struct LoadError {
+        #[source]
+        reason: std::io::Error,
+    }
+
+    let context = || LoadError {
+        reason: std::io::Error::new(std::io::ErrorKind::Other, "disk full"),
+    };
+
+    // The source of the context is lost in favor of the original error.
+    let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "oh no!");
+    let error = Err::<(), _>(io_error)
+        .with_context_err(context)
+        .unwrap_err();
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["failed to load config", "oh no!"], *messages);
+    let reason = &error.downcast_ref::<LoadError>().unwrap().reason;
+    assert_eq!("disk full", reason.to_string());
+
+    // Without an original error, the source of the context is kept.
+    let error = None::<()>.with_context_err(context).unwrap_err();
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["failed to load config", "disk full"], *messages);
+}
+
+#[test]
+fn test_with_context_err_on_anyhow() {
+    #[derive(Error, Debug)]
+    #[error("request {id} failed")]
+    struct RequestError {
+        id: u64,
+    }
+
+    let called = Cell::new(false);
+    let context = || {
+        called.set(true);
+        RequestError { id: 7 }
+    };
+
+    let ok: Result<i32> = Ok(1);
+    assert_eq!(1, ok.with_context_err(context).unwrap());
+    assert!(!called.get());
+
+    let failed: Result<()> = Err(Error::msg("oh no!").context("connection reset"));
+    let error = failed.with_context_err(context).unwrap_err();
+    assert!(called.get());
+    assert_eq!(7, error.downcast_ref::<RequestError>().unwrap().id);
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        ["request 7 failed", "connection reset", "oh no!"],
+        *messages
+    );
+
+    let request_error = error.downcast::<RequestError>().unwrap();
+    assert_eq!(7, request_error.id);
+}
+
+#[test]
+fn test_map_top() {
+    let (err, dropped) = make_chain();
+    let err = err.map_top(|message| message.replace("server", "[redacted]"));
+    assert_eq!("failed to start [redacted]", err.to_string());
+
+    let messages: Vec<_> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        [
+            "failed to start [redacted]",
+            "failed to load config",
+            "no such file or directory",
+        ],
+        *messages,
+    );
+    assert_eq!(
+        ["failed to start [redacted]", "failed to load config"],
+        *err.context_messages(),
+    );
+    assert!(!format!("{:?}", err).contains("server"));
+    assert!(!format!("{:#?}", err).contains("server"));
+
+    assert_eq!(
+        "failed to start [redacted]",
+        err.downcast_ref::<String>().unwrap()
+    );
+    assert!(err.is::<MidLevel>());
+    assert!(err.is::<LowLevel>());
+
+    let err = err.downcast::<MidLevel>().unwrap();
+    assert!(!dropped.mid.get());
+    assert!(dropped.low.get() && dropped.high.get());
+    drop(err);
+    assert!(dropped.all());
+}
+
+#[test]
+fn test_map_top_root() {
+    let _config = config::set(config::plain());
+
+    let err = Error::msg("/home/alice/secret not found").context("failed to load");
+    let err = err.map_top(|_| "redacted".to_owned());
+    assert_eq!(
+        "redacted\n\nCaused by:\n    /home/alice/secret not found",
+        format!("{:?}", err)
+    );
+
+    let err = Error::msg("/home/alice/secret not found").map_top(|_| "redacted".to_owned());
+    assert_eq!("redacted", format!("{:?}", err));
+    assert_eq!(1, err.chain().count());
+    assert!(err.context_messages().is_empty());
+    assert_eq!("redacted", err.downcast::<String>().unwrap());
+}
+
+#[test]
+fn test_context_if() {
+    let _config = config::set(config::plain());
+
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let err = result.context_if(true, || "context").unwrap_err();
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", err));
+
+    let evaluated = Cell::new(false);
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let err = result
+        .context_if(false, || {
+            evaluated.set(true);
+            "context"
+        })
+        .unwrap_err();
+    assert!(!evaluated.get());
+    assert_eq!("oh no!", format!("{:?}", err));
+    assert!(err.is::<io::Error>());
+
+    let result: Result<()> = Err(Error::msg("oh no!").context("outer"));
+    let err = result.context_if(false, || "context").unwrap_err();
+    assert_eq!(["outer", "oh no!"], *err.chain_messages());
+
+    let ok: Result<u8, io::Error> = Ok(1);
+    assert_eq!(
+        1,
+        ok.context_if(true, || -> &str { unreachable!() }).unwrap()
+    );
+
+    let err = None::<()>.context_if(false, || "context").unwrap_err();
+    assert_eq!("context", err.to_string());
+}
+
+#[test]
+fn test_push_context() {
+    let _config = config::set(config::plain());
+
+    let consumed = Error::msg("oh no!")
+        .with_code(2)
+        .context("middle")
+        .context(String::from("outer"));
+
+    let mut pushed = Error::msg("oh no!").with_code(2);
+    pushed.push_context("middle");
+    pushed.push_context(String::from("outer"));
+
+    assert_eq!(format!("{:?}", consumed), format!("{:?}", pushed));
+    assert_eq!(consumed.chain_messages(), pushed.chain_messages());
+    assert_eq!(Some(2), pushed.code());
+    assert_eq!("outer", pushed.downcast_ref::<String>().unwrap());
+    assert_eq!("middle", *pushed.downcast_ref::<&str>().unwrap());
+}
+
+#[test]
+fn test_context_capture() {
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let err = result.context_capture("context").unwrap_err();
+    assert_eq!("context", err.to_string());
+    assert!(err.is::<io::Error>());
+
+    let err = None::<()>.context_capture("context").unwrap_err();
+    assert_eq!("context", err.to_string());
+}
+
+#[test]
+fn test_into_inner() {
+    let _config = config::set(config::plain());
+
+    struct RequestId(u64);
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "oh no!"))
+        .context("failed to read config")
+        .context("failed to start")
+        .unwrap_err()
+        .with_code(2);
+    assert_eq!(3, error.chain().count());
+
+    let error = error.into_inner();
+    assert_eq!("oh no!", error.to_string());
+    assert_eq!(1, error.chain().count());
+    assert!(error.context_messages().is_empty());
+    assert_eq!(Some(2), error.code());
+    let io_error = error.downcast::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::NotFound, io_error.kind());
+
+    let mut error = Error::msg("oh no!").context("inner").context("outer");
+    error.insert_ext(RequestId(7));
+    let error = error.into_inner();
+    assert_eq!("oh no!", format!("{:?}", error));
+    assert_eq!(7, error.get_ext::<RequestId>().unwrap().0);
+
+    // An error without context is returned as it is.
+    let error = Error::msg("oh no!").into_inner();
+    assert_eq!("oh no!", error.to_string());
+    assert!(error.is::<&str>());
+
+    // A replaced message is not context, so it is kept.
+    let error = Error::msg("oh no!")
+        .context("inner")
+        .map_top(|message| message.to_uppercase())
+        .context("outer")
+        .into_inner();
+    assert_eq!("INNER", error.to_string());
+    assert_eq!(2, error.chain().count());
+
+    // Nothing is dropped twice or leaked.
+    let has_dropped = Flag::new();
+    let error = Error::new(DetectDrop::new(&has_dropped))
+        .context("inner")
+        .context("outer")
+        .into_inner();
+    assert!(!has_dropped.get());
+    drop(error);
+    assert!(has_dropped.get());
+}
+
+#[test]
+fn test_insert_context_at() {
+    let _config = config::set(config::plain());
+
+    struct RequestId(u64);
+
+    let io_error = io::Error::new(io::ErrorKind::NotFound, "not found");
+    let mut error = Err::<(), _>(io_error)
+        .context("failed to read config")
+        .context("failed to start server")
+        .unwrap_err();
+    error.insert_ext(RequestId(7));
+
+    let error = error.insert_context_at(2, "config.toml");
+    let expected = "\
+failed to start server
+
+Caused by:
+    0: failed to read config
+    1: config.toml
+    2: not found";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!(7, error.get_ext::<RequestId>().unwrap().0);
+    assert_eq!(
+        "failed to start server",
+        *error.downcast_ref::<&str>().unwrap()
+    );
+    assert!(error.is::<io::Error>());
+
+    // Depth 0 is the same as context, and a depth past the end inserts above
+    // the root cause.
+    let error = Error::msg("oh no!")
+        .insert_context_at(0, "outer")
+        .insert_context_at(9, "inner");
+    let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["outer", "inner", "oh no!"], *messages);
+
+    // Nothing is dropped twice or leaked.
+    let has_dropped = Flag::new();
+    let error = Err::<(), _>(DetectDrop::new(&has_dropped))
+        .context("outer")
+        .unwrap_err()
+        .insert_context_at(1, "inner");
+    assert_eq!(3, error.chain().count());
+    assert!(!has_dropped.get());
+    drop(error);
+    assert!(has_dropped.get());
+}
+
+#[test]
+fn test_context_all() {
+    let _config = config::set(config::plain());
+
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let err = result
+        .context_all(vec![
+            "failed to sync".to_owned(),
+            "remote: origin".to_owned(),
+            "branch: main".to_owned(),
+        ])
+        .context("failed to publish")
+        .unwrap_err();
+    let expected = "\
+failed to publish
+
+Caused by:
+    0: failed to sync
+remote: origin
+branch: main
+    1: oh no!";
+    assert_eq!(expected, format!("{:?}", err));
+
+    anyhow::set_format_config(config::plain().align_continuation(true));
+    let expected = "\
+failed to publish
+
+Caused by:
+    0: failed to sync
+       remote: origin
+       branch: main
+    1: oh no!";
+    assert_eq!(expected, format!("{:?}", err));
+    assert_eq!(3, err.chain().count());
+    assert!(err.is::<io::Error>());
+
+    let err = None::<()>
+        .context_all(["no value".to_owned(), "key: id".to_owned()])
+        .unwrap_err();
+    assert_eq!("no value\nkey: id", err.to_string());
+}
@@ -0,0 +1,34 @@
+#![cfg(all(feature = "backtrace-crate", not(feature = "wasm")))]
+
+use anyhow::{Context, FormatConfig};
+use std::io;
+
+#[inline(never)]
+fn read() -> Result<(), io::Error> {
+    Err(io::Error::new(io::ErrorKind::Other, "oh no!"))
+}
+
+// A single test, since whether backtraces are captured is decided once per
+// process from the environment.
+#[test]
+fn test_context_capture() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "0");
+
+    let error = read().context("context").unwrap_err();
+    assert_eq!(None, error.backtrace_string());
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+
+    // The backtrace is captured, but not printed unless asked for, since
+    // RUST_LIB_BACKTRACE=0 turns printing off too.
+    let error = read().context_capture("context").unwrap_err();
+    assert!(error
+        .backtrace_string()
+        .unwrap()
+        .contains("test_context_capture"));
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+
+    anyhow::set_format_config(FormatConfig::new().show_backtrace(true));
+    let debug = format!("{:?}", error);
+    assert!(debug.starts_with("context\n\nCaused by:\n    oh no!\n\nStack backtrace:\n"));
+    assert!(debug.contains("test_context_capture"));
+}
@@ -1,3 +1,4 @@
+mod config;
 mod drop;
 
 use self::drop::{DetectDrop, Flag};
@@ -22,3 +23,41 @@ fn test_question_mark() -> Result<(), Box<dyn StdError>> {
     f()?;
     Ok(())
 }
+
+#[test]
+fn test_msg_string() {
+    let _config = config::set(config::plain());
+
+    let message = format!("{} {}!", "oh", "no");
+    let error = Error::msg(message.clone());
+    assert_eq!(message, error.to_string());
+    assert_eq!(
+        format!("{:?}", Error::msg("oh no!")),
+        format!("{:?}", error)
+    );
+    assert_eq!(
+        format!("{:#}", Error::msg("oh no!")),
+        format!("{:#}", error)
+    );
+    assert_eq!(message, error.downcast::<String>().unwrap());
+}
+
+#[test]
+fn test_convert_keeps_chain() {
+    let _config = config::set(config::plain());
+
+    let error = Error::msg("oh no!").context("outer").with_code(3);
+    let debug = format!("{:?}", error);
+
+    let boxed = Box::<dyn StdError + Send + Sync>::from(error);
+    assert_eq!("outer", boxed.to_string());
+    assert_eq!("oh no!", boxed.source().unwrap().to_string());
+    assert_eq!(debug, format!("{:?}", boxed));
+
+    // Adopting the box again wraps it without a second conversion of the
+    // error inside, and it can be taken back out by downcasting.
+    let error = Error::from_boxed(boxed);
+    assert_eq!("outer", error.to_string());
+    let boxed = error.downcast::<Box<dyn StdError + Send + Sync>>().unwrap();
+    assert_eq!("outer", boxed.to_string());
+}
@@ -3,7 +3,7 @@ mod drop;
 
 use self::common::*;
 use self::drop::{DetectDrop, Flag};
-use anyhow::Error;
+use anyhow::{Context, Error};
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
 use std::io;
@@ -48,6 +48,43 @@ fn test_downcast_ref() {
     );
 }
 
+#[test]
+fn test_downcast_ref_or() {
+    let fallback = io::Error::new(io::ErrorKind::NotFound, "fallback");
+
+    let error = bail_error().unwrap_err();
+    let io_error = error.downcast_ref_or(&fallback);
+    assert_eq!(io::ErrorKind::Other, io_error.kind());
+    assert_eq!("oh no!", io_error.to_string());
+
+    let error = bail_literal().unwrap_err();
+    let io_error = error.downcast_ref_or(&fallback);
+    assert_eq!(io::ErrorKind::NotFound, io_error.kind());
+    assert_eq!("oh no!", *error.downcast_ref_or(&"fallback"));
+}
+
+#[test]
+fn test_downcast_ref_or_else() {
+    let fallback = io::Error::new(io::ErrorKind::NotFound, "fallback");
+    let mut called = false;
+
+    let error = bail_error().unwrap_err();
+    let io_error = error.downcast_ref_or_else(|| {
+        called = true;
+        &fallback
+    });
+    assert_eq!("oh no!", io_error.to_string());
+    assert!(!called);
+
+    let error = bail_fmt().unwrap_err();
+    let io_error = error.downcast_ref_or_else(|| {
+        called = true;
+        &fallback
+    });
+    assert_eq!(io::ErrorKind::NotFound, io_error.kind());
+    assert!(called);
+}
+
 #[test]
 fn test_downcast_mut() {
     assert_eq!(
@@ -104,3 +141,219 @@ fn test_unsuccessful_downcast() {
     assert!(error.downcast_mut::<&str>().is_none());
     assert!(error.downcast::<&str>().is_err());
 }
+
+#[test]
+fn test_unsuccessful_downcast_preserves_error() {
+    let error = bail_error()
+        .map_err(|error| error.context("context").with_code(5))
+        .unwrap_err();
+    let debug = format!("{:?}", error);
+    let display = format!("{:#}", error);
+
+    let error = error.downcast::<String>().unwrap_err();
+    let error = error.downcast::<io::ErrorKind>().unwrap_err();
+    assert_eq!(debug, format!("{:?}", error));
+    assert_eq!(display, format!("{:#}", error));
+    assert_eq!(Some(5), error.code());
+
+    // The error is still whole, so it can be downcast to what it holds.
+    assert_eq!("oh no!", error.downcast::<io::Error>().unwrap().to_string());
+}
+
+#[test]
+fn test_downcast_chain() {
+    #[derive(Debug)]
+    struct HttpError {
+        io: io::Error,
+    }
+
+    impl Display for HttpError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("request failed")
+        }
+    }
+
+    impl StdError for HttpError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.io)
+        }
+    }
+
+    let io = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    let error = Error::new(HttpError { io })
+        .context("failed to fetch")
+        .context("failed to sync");
+
+    assert!(error.downcast_ref::<io::Error>().is_none());
+    let io = error.downcast_chain_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::TimedOut, io.kind());
+    assert!(error.downcast_chain_ref::<HttpError>().is_some());
+    assert!(error.downcast_chain_ref::<fmt::Error>().is_none());
+
+    let cause = error.find_cause(|cause| cause.to_string() == "timed out");
+    assert!(cause.unwrap().is::<io::Error>());
+    assert!(error.find_cause(|cause| cause.is::<fmt::Error>()).is_none());
+}
+
+#[test]
+fn test_downcast_chain_mut() {
+    let io = io::Error::new(io::ErrorKind::NotFound, "not found");
+    let mut error = Err::<(), _>(io)
+        .context("failed to read")
+        .context("failed to load")
+        .unwrap_err();
+
+    let io = error.downcast_chain_mut::<io::Error>().unwrap();
+    *io = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    assert_eq!("timed out", error.root_cause().to_string());
+
+    let context = error.downcast_chain_mut::<&str>().unwrap();
+    assert_eq!("failed to load", *context);
+    *context = "failed to sync";
+    assert_eq!("failed to sync", error.to_string());
+    assert!(error.downcast_chain_mut::<fmt::Error>().is_none());
+
+    #[derive(Debug)]
+    struct Wrapper(io::Error);
+
+    impl Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl StdError for Wrapper {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    // A cause behind the source() of the underlying error cannot be borrowed
+    // mutably.
+    let io = io::Error::new(io::ErrorKind::NotFound, "not found");
+    let mut error = Error::new(Wrapper(io)).context("context");
+    assert!(error.downcast_chain_ref::<io::Error>().is_some());
+    assert!(error.downcast_chain_mut::<io::Error>().is_none());
+    assert!(error.downcast_chain_mut::<Wrapper>().is_some());
+}
+
+#[test]
+fn test_chain_contains() {
+    #[derive(Debug)]
+    struct Wrapper(io::Error);
+
+    impl Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl StdError for Wrapper {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let head = bail_error().unwrap_err();
+    assert!(head.chain_contains::<io::Error>());
+
+    let io = io::Error::new(io::ErrorKind::NotFound, "not found");
+    let middle = Error::new(Wrapper(io)).context("context");
+    assert!(middle.chain_contains::<Wrapper>());
+    assert!(middle.chain_contains::<io::Error>());
+
+    let absent = bail_literal().unwrap_err().context("context");
+    assert!(!absent.chain_contains::<io::Error>());
+}
+
+#[test]
+fn test_root_cause_is() {
+    let single = bail_error().unwrap_err();
+    assert!(single.root_cause().is::<io::Error>());
+    assert!(single.root_cause_is::<io::Error>());
+
+    let deep = single.context("f failed").context("g failed");
+    assert_eq!("oh no!", deep.root_cause().to_string());
+    assert!(deep.root_cause_is::<io::Error>());
+    assert!(!deep.root_cause_is::<fmt::Error>());
+}
+
+#[test]
+fn test_take_cause() {
+    #[derive(Debug)]
+    struct Inner;
+
+    impl Display for Inner {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("inner")
+        }
+    }
+
+    impl StdError for Inner {}
+
+    #[derive(Debug)]
+    struct Outer(Inner);
+
+    impl Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl StdError for Outer {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let error = Error::new(Outer(Inner)).context("context");
+
+    // Inner is only reachable through Outer's source(), so it stays put.
+    assert!(error.chain().any(|cause| cause.is::<Inner>()));
+    let error = error.take_cause::<Inner>().unwrap_err();
+    assert_eq!("context", error.to_string());
+    assert_eq!(3, error.chain().count());
+
+    let error = error.take_cause::<io::Error>().unwrap_err();
+    assert_eq!(3, error.chain().count());
+
+    let outer = error.take_cause::<Outer>().unwrap();
+    assert_eq!("inner", outer.0.to_string());
+
+    let error = Error::new(Outer(Inner)).context("context".to_owned());
+    assert_eq!("context", error.take_cause::<String>().unwrap());
+}
+
+#[test]
+fn test_matches() {
+    let io = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    let error = Error::new(io)
+        .context("failed to fetch")
+        .context("failed to sync");
+
+    // The head.
+    assert!(error.matches(|cause| cause.to_string() == "failed to sync"));
+    let head = error.find(|cause| cause.to_string().starts_with("failed"));
+    assert_eq!("failed to sync", head.unwrap().to_string());
+
+    // A middle cause.
+    assert!(error.matches(|cause| cause.to_string() == "failed to fetch"));
+    let middle = error.find(|cause| cause.to_string().ends_with("fetch"));
+    assert_eq!("failed to fetch", middle.unwrap().to_string());
+
+    // None.
+    let mut visited = 0;
+    assert!(!error.matches(|cause| {
+        visited += 1;
+        cause.is::<fmt::Error>()
+    }));
+    assert_eq!(3, visited);
+    let mut visited = 0;
+    assert!(error
+        .find(|cause| {
+            visited += 1;
+            cause.is::<fmt::Error>()
+        })
+        .is_none());
+    assert_eq!(3, visited);
+}
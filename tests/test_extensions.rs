@@ -0,0 +1,53 @@
+mod drop;
+
+use self::drop::{DetectDrop, Flag};
+use anyhow::{anyhow, Context, Error, Result};
+
+#[derive(Debug, PartialEq)]
+struct RequestId(u64);
+
+#[derive(Debug, PartialEq)]
+struct RetryAfter(u32);
+
+#[test]
+fn test_insert_get() {
+    let mut error = anyhow!("oh no!");
+    assert_eq!(None, error.get_ext::<RequestId>());
+
+    assert_eq!(None, error.insert_ext(RequestId(1)));
+    assert_eq!(Some(RequestId(1)), error.insert_ext(RequestId(2)));
+    error.insert_ext(RetryAfter(30));
+
+    assert_eq!(Some(&RequestId(2)), error.get_ext::<RequestId>());
+    assert_eq!(Some(&RetryAfter(30)), error.get_ext::<RetryAfter>());
+}
+
+#[test]
+fn test_survives_context() {
+    fn inner() -> Result<()> {
+        let mut error = anyhow!("oh no!");
+        error.insert_ext(RequestId(7));
+        Err(error)
+    }
+
+    let error = inner()
+        .context("mid-level")
+        .map_err(|error| error.context("high-level"))
+        .unwrap_err();
+    assert_eq!(Some(&RequestId(7)), error.get_ext::<RequestId>());
+}
+
+#[test]
+fn test_drop() {
+    let has_dropped = Flag::new();
+    let mut error = anyhow!("oh no!");
+    error.insert_ext(DetectDrop::new(&has_dropped));
+    drop(error.context("context"));
+    assert!(has_dropped.get());
+
+    let has_dropped = Flag::new();
+    let mut error = Error::msg("oh no!");
+    error.insert_ext(DetectDrop::new(&has_dropped));
+    assert_eq!("oh no!", error.downcast::<&str>().unwrap());
+    assert!(has_dropped.get());
+}
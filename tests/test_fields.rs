@@ -0,0 +1,46 @@
+use anyhow::{anyhow, FieldVisitor};
+use std::fmt::Display;
+
+#[derive(Default)]
+struct Collect {
+    fields: Vec<(String, String)>,
+}
+
+impl FieldVisitor for Collect {
+    fn record(&mut self, name: &str, value: &dyn Display) {
+        self.fields.push((name.to_owned(), value.to_string()));
+    }
+}
+
+fn fields(error: &anyhow::Error) -> Vec<(String, String)> {
+    let mut collect = Collect::default();
+    error.record_fields(&mut collect);
+    // Whether a backtrace is recorded depends on the toolchain.
+    collect
+        .fields
+        .into_iter()
+        .filter(|(name, _)| name != "error.backtrace")
+        .collect()
+}
+
+#[test]
+fn test_record_fields() {
+    let error = anyhow!("root").context("middle").context("outer");
+    let expected = [
+        ("error.message", "outer"),
+        ("error.chain.0", "middle"),
+        ("error.chain.1", "root"),
+    ];
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+    assert_eq!(expected, fields(&error));
+}
+
+#[test]
+fn test_record_fields_no_causes() {
+    let error = anyhow!("oh no!");
+    let expected = vec![("error.message".to_owned(), "oh no!".to_owned())];
+    assert_eq!(expected, fields(&error));
+}
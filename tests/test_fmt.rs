@@ -1,4 +1,8 @@
-use anyhow::{bail, Context, Result};
+mod config;
+
+use anyhow::{bail, Context, Error, FormatConfig, Result};
+use std::collections::VecDeque;
+use std::fmt;
 use std::io;
 
 fn f() -> Result<()> {
@@ -36,6 +40,23 @@ Caused by:
     1: oh no!\
 ";
 
+const EXPECTED_PLUSDEBUG_F: &str = "oh no!";
+
+const EXPECTED_PLUSDEBUG_G: &str = "\
+oh no!
+
+Which caused:
+    f failed\
+";
+
+const EXPECTED_PLUSDEBUG_H: &str = "\
+oh no!
+
+Which caused:
+    0: f failed
+    1: g failed\
+";
+
 const EXPECTED_ALTDEBUG_F: &str = "\
 Custom {
     kind: PermissionDenied,
@@ -86,9 +107,195 @@ fn test_debug() {
     assert_eq!(EXPECTED_DEBUG_H, format!("{:?}", h().unwrap_err()));
 }
 
+#[test]
+fn test_plusdebug() {
+    let _config = config::set(config::plain());
+
+    assert_eq!(EXPECTED_PLUSDEBUG_F, format!("{:+?}", f().unwrap_err()));
+    assert_eq!(EXPECTED_PLUSDEBUG_G, format!("{:+?}", g().unwrap_err()));
+    assert_eq!(EXPECTED_PLUSDEBUG_H, format!("{:+?}", h().unwrap_err()));
+    assert_eq!(EXPECTED_DEBUG_H, format!("{:?}", h().unwrap_err()));
+}
+
 #[test]
 fn test_altdebug() {
     assert_eq!(EXPECTED_ALTDEBUG_F, format!("{:#?}", f().unwrap_err()));
     assert_eq!(EXPECTED_ALTDEBUG_G, format!("{:#?}", g().unwrap_err()));
     assert_eq!(EXPECTED_ALTDEBUG_H, format!("{:#?}", h().unwrap_err()));
 }
+
+#[test]
+fn test_display_with_separator() {
+    let error = h().unwrap_err();
+    assert_eq!(
+        "g failed -> f failed -> oh no!",
+        error.display_with_separator(" -> ").to_string(),
+    );
+    assert_eq!(
+        "g failed | f failed | oh no!",
+        format!("{}", error.display_with_separator(" | ")),
+    );
+    assert_eq!(EXPECTED_ALTDISPLAY_H, format!("{:#}", error));
+}
+
+#[test]
+fn test_root_cause_first_last() {
+    let _config = config::set(config::plain());
+
+    let error = h().unwrap_err();
+    assert_eq!(EXPECTED_DEBUG_H, format!("{:?}", error.root_cause_first()));
+    assert_eq!(
+        EXPECTED_PLUSDEBUG_H,
+        format!("{:?}", error.root_cause_last())
+    );
+
+    anyhow::set_format_config(FormatConfig::new().show_backtrace(true));
+
+    let backtrace = "Stack backtrace:\n   0: worker::main\n             at src/main.rs:4";
+    let error = Error::from_parts(
+        vec!["failed to sync".to_owned(), "connection reset".to_owned()],
+        Some(backtrace.to_owned()),
+    )
+    .with_section("Note:", "retrying".to_owned());
+    let first = format!("{:?}", error.root_cause_first());
+    let last = format!("{:?}", error.root_cause_last());
+    assert_ne!(first, last);
+    assert_eq!(
+        format!(
+            "failed to sync\n\nCaused by:\n    connection reset\n\nNote:\n    retrying\n\n{}",
+            backtrace,
+        ),
+        first,
+    );
+    assert_eq!(
+        format!(
+            "connection reset\n\nWhich caused:\n    failed to sync\n\nNote:\n    retrying\n\n{}",
+            backtrace,
+        ),
+        last,
+    );
+}
+
+#[test]
+fn test_short() {
+    assert_eq!("g failed", h().unwrap_err().short());
+
+    let error = Error::msg("  failed to parse config:\n\n    unexpected `}`\r\n  ");
+    assert_eq!("failed to parse config: unexpected `}`", error.short());
+    let error = error.context("failed to start");
+    assert_eq!("failed to start", error.short());
+}
+
+#[test]
+fn test_display_indexed() {
+    let error = h().unwrap_err();
+    assert_eq!(
+        "g failed (1: f failed) (2: oh no!)",
+        error.display_indexed().to_string(),
+    );
+    assert_eq!(EXPECTED_ALTDISPLAY_H, format!("{:#}", error));
+    assert_eq!("oh no!", f().unwrap_err().display_indexed().to_string());
+}
+
+#[test]
+fn test_to_markdown() {
+    let _config = config::set(config::plain().show_backtrace(true));
+
+    let backtrace = "Stack backtrace:\n   0: app::main\n             at src/main.rs:4";
+    let error = Error::from_parts(
+        vec![
+            "failed to load *config*".to_owned(),
+            "key `port` is not a number\nfound: <8080>".to_owned(),
+        ],
+        Some(backtrace.to_owned()),
+    );
+    let expected = "\
+### failed to load \\*config\\*
+
+1. key \\`port\\` is not a number
+   found: \\<8080\\>
+
+```text
+Stack backtrace:
+   0: app::main
+             at src/main.rs:4
+```
+";
+    assert_eq!(expected, error.to_markdown());
+
+    // Like Debug, the Markdown leaves out a backtrace that is not shown.
+    anyhow::set_format_config(config::plain());
+    let expected = "\
+### failed to load \\*config\\*
+
+1. key \\`port\\` is not a number
+   found: \\<8080\\>
+";
+    assert_eq!(expected, error.to_markdown());
+
+    let expected = "\
+### g failed
+
+1. f failed
+2. oh no!
+";
+    assert_eq!(expected, h().unwrap_err().to_markdown());
+}
+
+#[test]
+fn test_report() {
+    let _config = config::set(config::plain());
+
+    assert_eq!(EXPECTED_DEBUG_F, f().unwrap_err().report());
+    assert_eq!(EXPECTED_DEBUG_G, g().unwrap_err().report());
+    assert_eq!(EXPECTED_DEBUG_H, h().unwrap_err().report());
+
+    let error = h().unwrap_err();
+    assert_eq!(format!("{:?}", error), error.report());
+}
+
+#[test]
+fn test_write_debug() {
+    let _config = config::set(config::plain());
+
+    // A sink that keeps only the last few bytes, like a ring buffer, and
+    // counts what went through it.
+    struct Tail {
+        bytes: VecDeque<u8>,
+        capacity: usize,
+        written: usize,
+    }
+
+    impl fmt::Write for Tail {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for &byte in s.as_bytes() {
+                if self.bytes.len() == self.capacity {
+                    self.bytes.pop_front();
+                }
+                self.bytes.push_back(byte);
+            }
+            self.written += s.len();
+            Ok(())
+        }
+    }
+
+    let backtrace = "Stack backtrace:\n   0: app::main\n             at src/main.rs:4";
+    let messages = (0..50).map(|i| format!("layer {}", i)).collect();
+    let error = Error::from_parts(messages, Some(backtrace.to_owned()))
+        .with_section("Note:", "multi\nline".to_owned());
+    let expected = format!("{:?}", error);
+
+    let mut tail = Tail {
+        bytes: VecDeque::new(),
+        capacity: 64,
+        written: 0,
+    };
+    error.write_debug(&mut tail).unwrap();
+    assert_eq!(expected.len(), tail.written);
+    let (_, end) = expected.split_at(expected.len() - 64);
+    assert_eq!(end.as_bytes(), &*Vec::from(tail.bytes));
+
+    let mut whole = String::new();
+    error.write_debug(&mut whole).unwrap();
+    assert_eq!(expected, whole);
+}
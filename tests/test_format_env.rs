@@ -0,0 +1,41 @@
+mod config;
+
+use anyhow::anyhow;
+use std::env;
+
+const ROOT_FIRST: &str = "g failed\n\nCaused by:\n    0: f failed\n    1: oh no!";
+const ROOT_LAST: &str = "oh no!\n\nWhich caused:\n    0: f failed\n    1: g failed";
+
+fn debug() -> String {
+    let error = anyhow!("oh no!").context("f failed").context("g failed");
+    format!("{:?}", error)
+}
+
+fn env_with(value: Option<&str>) -> impl FnOnce(&str) -> Option<String> + '_ {
+    move |name| {
+        assert_eq!("ANYHOW_FORMAT", name);
+        value.map(str::to_owned)
+    }
+}
+
+// A single test, since the environment is read once per process.
+#[test]
+fn test_format_env() {
+    let _config = config::set(config::plain());
+
+    env::set_var("ANYHOW_FORMAT", "root-last");
+    assert_eq!(ROOT_LAST, debug());
+
+    // The variable is not read again.
+    env::set_var("ANYHOW_FORMAT", "root-first");
+    assert_eq!(ROOT_LAST, debug());
+
+    assert!(anyhow::formatter_from_env(env_with(None)).is_none());
+    assert!(anyhow::formatter_from_env(env_with(Some("sideways"))).is_none());
+    assert!(anyhow::formatter_from_env(env_with(Some("root-last"))).is_some());
+
+    // An installed formatter takes precedence.
+    let formatter = anyhow::formatter_from_env(env_with(Some("root-first"))).unwrap();
+    anyhow::set_formatter(formatter);
+    assert_eq!(ROOT_FIRST, debug());
+}
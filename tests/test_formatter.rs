@@ -0,0 +1,29 @@
+use anyhow::{anyhow, ErrorFormatter, ErrorInfo};
+use std::fmt;
+
+struct Tree;
+
+impl ErrorFormatter for Tree {
+    fn fmt_error(&self, info: &ErrorInfo, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", info.error())?;
+        let mut source = info.error().source();
+        while let Some(cause) = source {
+            write!(f, "\n└─ {}", cause)?;
+            source = cause.source();
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_set_formatter() {
+    let error = anyhow!("oh no!").context("f failed").context("g failed");
+    let default = format!("{:?}", error);
+    assert!(default.contains("Caused by:"));
+
+    anyhow::set_formatter(Box::new(Tree));
+    assert_eq!("g failed\n└─ f failed\n└─ oh no!", format!("{:?}", error));
+
+    // The alternate Debug representation is not affected.
+    assert!(format!("{:#?}", error).starts_with("Error {"));
+}
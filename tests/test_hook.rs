@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Context, Result};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn count() -> usize {
+    COUNT.load(Ordering::SeqCst)
+}
+
+fn io_error() -> Result<(), io::Error> {
+    Err(io::Error::new(io::ErrorKind::Other, "oh no!"))
+}
+
+fn convert() -> Result<()> {
+    io_error()?;
+    Ok(())
+}
+
+// A single test, since the hook is global to the process.
+#[test]
+fn test_hook() {
+    anyhow::set_error_hook(Box::new(|error| {
+        COUNT.fetch_add(1, Ordering::SeqCst);
+        // Errors created inside the hook do not invoke it again.
+        let _ = anyhow!("inside the hook: {}", error);
+    }));
+
+    let error = anyhow!("oh no!");
+    assert_eq!(1, count());
+
+    let error = error.context("context");
+    assert_eq!(2, count());
+
+    let _ = error.context("more context").context("even more");
+    assert_eq!(4, count());
+
+    let _ = convert().unwrap_err();
+    assert_eq!(5, count());
+
+    let error = io_error().context("context").unwrap_err();
+    assert_eq!(6, count());
+
+    // Taking the context off again does not create a new error.
+    let error = error.into_inner();
+    assert_eq!(6, count());
+    assert!(error.is::<io::Error>());
+
+    // Inserting context below other context runs the hook for the new layer
+    // only, not for the layers rebuilt to make room for it.
+    let error = io_error().context("outer").unwrap_err();
+    assert_eq!(7, count());
+    let error = error.insert_context_at(1, "inner");
+    assert_eq!(8, count());
+    assert_eq!("outer: inner: oh no!", format!("{:#}", error));
+
+    Some(()).context("not an error").unwrap();
+    assert_eq!(8, count());
+}
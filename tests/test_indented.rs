@@ -0,0 +1,66 @@
+use anyhow::{GutterStyle, Indented};
+use std::fmt::Write;
+
+#[test]
+fn one_digit() {
+    let mut output = String::new();
+    Indented::numbered(&mut output, 2)
+        .write_str("verify\nthis")
+        .unwrap();
+    assert_eq!("    2: verify\n       this", output);
+}
+
+#[test]
+fn two_digits() {
+    let mut output = String::new();
+    Indented::numbered(&mut output, 12)
+        .write_str("verify\nthis")
+        .unwrap();
+    assert_eq!("    12: verify\n        this", output);
+}
+
+#[test]
+fn no_digits() {
+    let mut output = String::new();
+    Indented::new(&mut output)
+        .write_str("verify\nthis")
+        .unwrap();
+    assert_eq!("    verify\n    this", output);
+}
+
+#[test]
+fn empty_lines() {
+    let mut output = String::new();
+    Indented::numbered(&mut output, 0)
+        .write_str("verify\n\nthis")
+        .unwrap();
+    assert_eq!("    0: verify\n\n       this", output);
+}
+
+#[test]
+fn split_writes() {
+    let mut output = String::new();
+    let mut indented = Indented::numbered(&mut output, 1).indent(2);
+    let (first, second) = ("verify", "this");
+    write!(indented, "{}\n{}", first, second).unwrap();
+    indented.write_str(" too\nand this").unwrap();
+    assert_eq!("  1: verify\n     this too\n     and this", output);
+}
+
+#[test]
+fn symbols() {
+    let mut output = String::new();
+    Indented::numbered(&mut output, 5)
+        .gutter(GutterStyle::Arrow)
+        .write_str("verify\nthis")
+        .unwrap();
+    assert_eq!("    ↳ verify\n      this", output);
+
+    // Without a number there is no marker in any style.
+    let mut output = String::new();
+    Indented::new(&mut output)
+        .gutter(GutterStyle::Bullet)
+        .write_str("verify\nthis")
+        .unwrap();
+    assert_eq!("    verify\n    this", output);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "serde")]
+
+use anyhow::{anyhow, Context, Error, Result};
+use serde_json::Value;
+
+fn f() -> Result<()> {
+    Err(anyhow!("first line\nsecond line"))
+}
+
+fn g() -> Result<()> {
+    f().context("f failed")
+}
+
+// The JSON of `error` without its backtrace, which is there or not depending
+// on how the tests are run, and the backtrace.
+fn split_backtrace(error: &Error, json: &str) -> (Value, Option<String>) {
+    let mut value: Value = serde_json::from_str(json).unwrap();
+    let backtrace = value.as_object_mut().unwrap().remove("backtrace");
+    let backtrace = backtrace.map(|backtrace| backtrace.as_str().unwrap().to_owned());
+    assert_eq!(error.backtrace_string(), backtrace);
+    (value, backtrace)
+}
+
+#[test]
+fn test_to_json() {
+    let error = f().unwrap_err();
+    let expected = r#"{"chain":[{"message":"first line\nsecond line","source_index":0}]}"#;
+    let expected: Value = serde_json::from_str(expected).unwrap();
+    assert_eq!(expected, split_backtrace(&error, &error.to_json()).0);
+
+    let error = g().unwrap_err();
+    let expected = concat!(
+        r#"{"chain":[{"message":"f failed","source_index":0},"#,
+        r#"{"message":"first line\nsecond line","source_index":1}]}"#,
+    );
+    let expected: Value = serde_json::from_str(expected).unwrap();
+    assert_eq!(expected, split_backtrace(&error, &error.to_json()).0);
+}
+
+#[test]
+fn test_to_json_backtrace() {
+    let error = anyhow!("oh no!").ensure_backtrace();
+    let (_, backtrace) = split_backtrace(&error, &error.to_json());
+    if let Some(backtrace) = backtrace {
+        assert!(backtrace.starts_with("Stack backtrace:\n"));
+    }
+}
+
+#[test]
+fn test_serialize() {
+    let error = g().unwrap_err();
+    let expected = r#"{"message":"f failed","causes":["first line\nsecond line"]}"#;
+    let expected: Value = serde_json::from_str(expected).unwrap();
+    let json = serde_json::to_string(&error).unwrap();
+    assert_eq!(expected, split_backtrace(&error, &json).0);
+
+    let error = f().unwrap_err();
+    let expected = r#"{"message":"first line\nsecond line","causes":[]}"#;
+    let expected: Value = serde_json::from_str(expected).unwrap();
+    let json = serde_json::to_string(&error).unwrap();
+    assert_eq!(expected, split_backtrace(&error, &json).0);
+}
+
+#[test]
+fn test_serialize_backtrace() {
+    let error = anyhow!("oh no!").ensure_backtrace();
+    let json = serde_json::to_string(&error).unwrap();
+    let (_, backtrace) = split_backtrace(&error, &json);
+    if let Some(backtrace) = backtrace {
+        assert!(backtrace.starts_with("Stack backtrace:\n"));
+    }
+}
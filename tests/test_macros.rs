@@ -2,6 +2,7 @@ mod common;
 
 use self::common::*;
 use anyhow::ensure;
+use std::cell::Cell;
 
 #[test]
 fn test_messages() {
@@ -31,3 +32,164 @@ fn test_ensure() {
     };
     assert!(f().is_err());
 }
+
+#[test]
+fn test_ensure_else() {
+    #[derive(Debug)]
+    struct TooBig(usize);
+
+    impl std::fmt::Display for TooBig {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{} is too big", self.0)
+        }
+    }
+
+    impl std::error::Error for TooBig {}
+
+    let evaluated = Cell::new(false);
+    let f = |v: usize| -> anyhow::Result<()> {
+        ensure!(v < 10, else => {
+            evaluated.set(true);
+            TooBig(v)
+        });
+        Ok(())
+    };
+    assert!(f(1).is_ok());
+    assert!(!evaluated.get());
+
+    let error = f(11).unwrap_err();
+    assert!(evaluated.get());
+    assert_eq!("11 is too big", error.to_string());
+    assert_eq!(11, error.downcast::<TooBig>().unwrap().0);
+
+    let g = |v: usize| -> Result<(), TooBig> {
+        ensure!(v < 10, else => TooBig(v));
+        Ok(())
+    };
+    assert_eq!(12, g(12).unwrap_err().0);
+}
+
+#[test]
+fn test_boundary() {
+    use anyhow::{boundary, Result};
+    use std::io;
+
+    fn load(fail: bool) -> Result<i32> {
+        let result = if fail {
+            Err(io::Error::new(io::ErrorKind::Other, "oh no!"))
+        } else {
+            Ok(1)
+        };
+        let value = boundary!(result)?;
+        Ok(value)
+    }
+
+    assert_eq!(1, load(false).unwrap());
+    let error = load(true).unwrap_err();
+    assert_eq!("oh no!", error.to_string());
+    assert!(error.is::<io::Error>());
+}
+
+#[test]
+fn test_ensure_eq() {
+    use anyhow::{ensure_eq, Result};
+
+    fn f(v: i32) -> Result<()> {
+        ensure_eq!(v, 1);
+        Ok(())
+    }
+    assert!(f(1).is_ok());
+    assert_eq!(
+        "Condition failed: `v == 1` (2 vs 1)",
+        f(2).unwrap_err().to_string(),
+    );
+
+    let evaluated = Cell::new(0);
+    let g = |s: &str| -> Result<()> {
+        ensure_eq!(
+            {
+                evaluated.set(evaluated.get() + 1);
+                s.len()
+            },
+            3,
+            "expected {} characters in {:?}",
+            3,
+            s,
+        );
+        Ok(())
+    };
+    assert!(g("abc").is_ok());
+    assert_eq!(1, evaluated.get());
+    assert_eq!(
+        "expected 3 characters in \"ab\" (2 vs 3)",
+        g("ab").unwrap_err().to_string(),
+    );
+    assert_eq!(2, evaluated.get());
+}
+
+#[test]
+fn test_ensure_ne() {
+    use anyhow::{ensure_ne, Result};
+
+    fn f(v: &str) -> Result<()> {
+        ensure_ne!(v, "");
+        Ok(())
+    }
+    assert!(f("a").is_ok());
+    assert_eq!(
+        "Condition failed: `v != \"\"` (\"\" vs \"\")",
+        f("").unwrap_err().to_string(),
+    );
+
+    fn g(v: Option<i32>) -> Result<()> {
+        ensure_ne!(v, None, "missing value");
+        Ok(())
+    }
+    assert!(g(Some(1)).is_ok());
+    assert_eq!(
+        "missing value (None vs None)",
+        g(None).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_bail_resolution() {
+    use anyhow::{anyhow, bail, Result};
+    use std::error::Error as StdError;
+    use std::io;
+    use thiserror::Error;
+
+    #[derive(Error, Debug)]
+    #[error("outer")]
+    struct Outer(#[source] io::Error);
+
+    // An error keeps its type and its source.
+    let error = bail_error().unwrap_err();
+    assert!(error.is::<io::Error>());
+    let f = || -> Result<()> { bail!(Outer(io::Error::new(io::ErrorKind::Other, "oh no!"))) };
+    let error = f().unwrap_err();
+    assert!(error.is::<Outer>());
+    assert_eq!("oh no!", error.root_cause().to_string());
+
+    // A literal or any other Display value is a message.
+    let error = bail_literal().unwrap_err();
+    assert!(error.is::<&str>());
+    let f = || -> Result<()> { bail!(String::from("oh no!")) };
+    assert!(f().unwrap_err().is::<String>());
+
+    // An anyhow::Error is passed through.
+    let f = || -> Result<()> { bail!(anyhow!("oh no!").context("context")) };
+    assert_eq!(2, f().unwrap_err().chain().count());
+
+    // A boxed error can be downcast to the box and then to the type inside.
+    let f = || -> Result<()> {
+        let boxed: Box<dyn StdError + Send + Sync> =
+            Box::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+        bail!(boxed)
+    };
+    let boxed = f()
+        .unwrap_err()
+        .downcast::<Box<dyn StdError + Send + Sync>>()
+        .unwrap();
+    assert!(boxed.is::<io::Error>());
+}
@@ -0,0 +1,292 @@
+mod config;
+
+use anyhow::{anyhow, bail, bail_code, Context, Error, Result, Severity};
+use std::io;
+
+fn f() -> Result<()> {
+    Err(anyhow!("oh no!").with_severity(Severity::Info))
+}
+
+#[test]
+fn test_severity() {
+    assert_eq!(None, anyhow!("oh no!").severity());
+
+    let error = f().context("f failed").unwrap_err();
+    assert_eq!(Some(Severity::Info), error.severity());
+
+    let error = error.with_severity(Severity::Fatal).context("g failed");
+    assert_eq!(Some(Severity::Fatal), error.severity());
+}
+
+#[test]
+fn test_severity_debug() {
+    let _config = config::set(config::plain());
+
+    let error = f().context("f failed").unwrap_err();
+    let expected = "\
+[INFO] f failed
+
+Caused by:
+    oh no!";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_retryable() {
+    assert!(!anyhow!("oh no!").is_retryable());
+
+    let leaf = anyhow!("connection reset").retryable(true);
+    let error = Err::<(), _>(leaf)
+        .context("f failed")
+        .context("g failed")
+        .unwrap_err();
+    assert!(error.is_retryable());
+
+    let error = error.retryable(false).context("h failed");
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_code() {
+    assert_eq!(None, anyhow!("x").code());
+    assert_eq!(Some(5), anyhow!("x").with_code(5).code());
+
+    let error = anyhow!("x").with_code(5).context("context");
+    assert_eq!(Some(5), error.code());
+    assert_eq!(Some(6), error.with_code(6).code());
+}
+
+#[test]
+fn test_code_debug() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("not found").with_code(5).context("lookup failed");
+    let expected = "\
+lookup failed (code 5)
+
+Caused by:
+    not found";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_bail_code() {
+    fn literal() -> Result<()> {
+        bail_code!(42, "oh no!");
+    }
+
+    fn fmt() -> Result<()> {
+        bail_code!(43, "{} {}!", "oh", "no");
+    }
+
+    fn error() -> Result<()> {
+        bail_code!(44, io::Error::new(io::ErrorKind::Other, "oh no!"));
+    }
+
+    for (f, code) in [literal as fn() -> Result<()>, fmt, error].iter().zip(42..) {
+        let error = f().unwrap_err();
+        assert_eq!("oh no!", error.to_string());
+        assert_eq!(Some(code), error.code());
+    }
+    assert!(error().unwrap_err().is::<io::Error>());
+}
+
+#[test]
+fn test_bail_metadata() {
+    fn retryable() -> Result<()> {
+        bail!(retryable: true, "{} {}!", "oh", "no");
+    }
+
+    fn severity() -> Result<()> {
+        bail!(severity: Severity::Warn, io::Error::new(io::ErrorKind::Other, "oh no!"));
+    }
+
+    fn both() -> Result<()> {
+        bail!(severity: Severity::Fatal, retryable: true, "oh no!");
+    }
+
+    let error = retryable().unwrap_err();
+    assert_eq!("oh no!", error.to_string());
+    assert!(error.is_retryable());
+    assert_eq!(None, error.severity());
+
+    let error = severity().unwrap_err();
+    assert_eq!(Some(Severity::Warn), error.severity());
+    assert!(!error.is_retryable());
+    assert!(error.is::<io::Error>());
+
+    let error = both().unwrap_err();
+    assert_eq!("oh no!", error.to_string());
+    assert_eq!(Some(Severity::Fatal), error.severity());
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn test_sections() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("oh no!")
+        .with_section("Note:", "the disk was full".to_owned())
+        .context("failed to save")
+        .with_section("Suggestion:", "free some space\nthen retry".to_owned());
+
+    let expected = "\
+failed to save
+
+Caused by:
+    oh no!
+
+Note:
+    the disk was full
+
+Suggestion:
+    free some space
+    then retry";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let expected = "\
+oh no!
+
+Which caused:
+    failed to save
+
+Note:
+    the disk was full
+
+Suggestion:
+    free some space
+    then retry";
+    assert_eq!(expected, format!("{:+?}", error));
+
+    assert_eq!("failed to save", error.to_string());
+    assert_eq!("oh no!", format!("{:?}", anyhow!("oh no!")));
+}
+
+#[test]
+fn test_suggestions() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("output file exists")
+        .suggestion("try running with --force")
+        .context("failed to export")
+        .suggestion(String::from("or pick another --output"));
+
+    let expected = "\
+failed to export
+
+Caused by:
+    output file exists
+
+Suggestion:
+    try running with --force
+
+Suggestion:
+    or pick another --output";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!(2, error.chain().count());
+    assert_eq!(
+        "failed to export: output file exists",
+        format!("{:#}", error)
+    );
+}
+
+#[test]
+fn test_help_urls() {
+    let _config = config::set(config::plain().show_backtrace(true));
+
+    let backtrace = "Stack backtrace:\n   0: app::main\n             at src/main.rs:4";
+    let error = Error::from_parts(
+        vec!["invalid manifest".to_owned()],
+        Some(backtrace.to_owned()),
+    )
+    .with_help_url("https://docs.example/errors/E123")
+    .context("failed to build")
+    .suggestion("run `app fix`")
+    .with_help_url(String::from("https://docs.example/manifest"));
+
+    let expected = format!(
+        "\
+failed to build
+
+Caused by:
+    invalid manifest
+
+Suggestion:
+    run `app fix`
+
+See: https://docs.example/errors/E123
+See: https://docs.example/manifest
+
+{}",
+        backtrace,
+    );
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!("failed to build: invalid manifest", format!("{:#}", error));
+    assert_eq!(
+        format!("{:?}", error),
+        format!("{:?}", error.clone_snapshot())
+    );
+
+    anyhow::set_format_config(config::plain());
+    let plain = anyhow!("invalid manifest");
+    assert_eq!("invalid manifest", format!("{:?}", plain));
+}
+
+#[test]
+fn test_builder() {
+    let _config = config::set(config::plain());
+
+    let error = Error::build("upstream unavailable")
+        .code(503)
+        .severity(Severity::Warn)
+        .retryable(true)
+        .suggestion("try again later")
+        .help_url("https://docs.example/errors/upstream")
+        .finish();
+
+    assert_eq!(Some(503), error.code());
+    assert_eq!(Some(Severity::Warn), error.severity());
+    assert!(error.is_retryable());
+    assert_eq!("upstream unavailable", error.to_string());
+    assert!(error.is::<&str>());
+
+    let expected = "\
+[WARN] upstream unavailable (code 503)
+
+Suggestion:
+    try again later
+
+See: https://docs.example/errors/upstream";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let built = Error::build("not found").finish();
+    assert_eq!(None, built.code());
+    assert_eq!(None, built.severity());
+    assert!(!built.is_retryable());
+    assert_eq!("not found", format!("{:?}", built));
+}
+
+#[test]
+fn test_attachments() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("worker crashed")
+        .attach_named("config.toml", b"threads = 8\n".to_vec())
+        .context("job failed")
+        .attach_named("log.txt", b"started\npanicked\n".to_vec());
+
+    let attachments = error.attachments();
+    assert_eq!(2, attachments.len());
+    assert_eq!("config.toml", attachments[0].0);
+    assert_eq!(b"threads = 8\n", &*attachments[0].1);
+    assert_eq!("log.txt", attachments[1].0);
+    assert_eq!(b"started\npanicked\n", &*attachments[1].1);
+
+    // They are left out of every representation.
+    let expected = "job failed\n\nCaused by:\n    worker crashed";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!("job failed: worker crashed", format!("{:#}", error));
+    assert!(!format!("{:#?}", error).contains("threads"));
+
+    assert!(anyhow!("oh no!").attachments().is_empty());
+}
@@ -0,0 +1,66 @@
+mod config;
+
+use anyhow::{anyhow, Error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+// Replace the value of every `token=` with asterisks.
+fn redact(output: &mut String) {
+    CALLS.fetch_add(1, Ordering::Relaxed);
+    let mut redacted = String::with_capacity(output.len());
+    let mut rest = output.as_str();
+    while let Some(start) = rest.find("token=") {
+        let (before, after) = rest.split_at(start + "token=".len());
+        redacted.push_str(before);
+        let end = after
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(after.len());
+        redacted.push_str("***");
+        rest = &after[end..];
+    }
+    redacted.push_str(rest);
+    *output = redacted;
+}
+
+#[test]
+fn test_post_processor() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("unauthorized: token=abc123")
+        .context("request to https://example.com?token=abc123 failed");
+
+    let before = format!("{:?}", error);
+    assert!(before.contains("abc123"));
+
+    anyhow::set_debug_post_processor(Box::new(redact));
+
+    let expected = "\
+request to https://example.com?token=*** failed
+
+Caused by:
+    unauthorized: token=***";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!(expected, error.report());
+    assert_eq!(expected, format!("{:?}", error.root_cause_first()));
+
+    let expected = "\
+unauthorized: token=***
+
+Which caused:
+    request to https://example.com?token=*** failed";
+    assert_eq!(expected, format!("{:+?}", error));
+    assert_eq!(expected, format!("{:?}", error.root_cause_last()));
+
+    assert!(!format!("{:#?}", error).contains("abc123"));
+
+    // Display is not post-processed.
+    assert_eq!("unauthorized: token=abc123", error.root_cause().to_string());
+
+    // The members of an aggregate are processed along with it, once.
+    let aggregate = Error::aggregate(vec![anyhow!("token=abc123"), anyhow!("token=def456")]);
+    let calls = CALLS.load(Ordering::Relaxed);
+    let expected = "2 errors occurred\n\nErrors:\n    0: token=***\n    1: token=***";
+    assert_eq!(expected, format!("{:?}", aggregate));
+    assert_eq!(calls + 1, CALLS.load(Ordering::Relaxed));
+}
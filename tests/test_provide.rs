@@ -0,0 +1,45 @@
+#![cfg(provide)]
+#![feature(error_generic_member_access)]
+
+use anyhow::{anyhow, Context, Result};
+use std::error::{self, Request};
+use std::fmt::{self, Display};
+
+#[derive(Debug, PartialEq)]
+struct RequestId(u64);
+
+#[derive(Debug)]
+struct Upstream {
+    id: RequestId,
+}
+
+impl Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("upstream failed")
+    }
+}
+
+impl error::Error for Upstream {
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        request.provide_ref(&self.id);
+    }
+}
+
+fn call() -> Result<()> {
+    Err(Upstream { id: RequestId(7) }).context("call failed")
+}
+
+#[test]
+fn test_provide_through_context() {
+    let error = call().context("handler failed").unwrap_err();
+    let provided = error::request_ref::<RequestId>(&*error);
+    assert_eq!(Some(&RequestId(7)), provided);
+
+    let boxed = Box::<dyn error::Error + Send + Sync>::from(error);
+    let provided = error::request_ref::<RequestId>(&*boxed);
+    assert_eq!(Some(&RequestId(7)), provided);
+
+    let adopted = anyhow!(boxed).context("outer");
+    let provided = error::request_ref::<RequestId>(&*adopted);
+    assert_eq!(Some(&RequestId(7)), provided);
+}
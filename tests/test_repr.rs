@@ -27,3 +27,12 @@ fn test_drop() {
     drop(Error::new(DetectDrop::new(&has_dropped)));
     assert!(has_dropped.get());
 }
+
+#[test]
+fn test_ptr_eq() {
+    let error = Error::msg("oh no!");
+    let other = Error::msg("oh no!");
+    assert!(error.ptr_eq(&error));
+    assert!(!error.ptr_eq(&other));
+    assert!(!error.ptr_eq(&error.clone_snapshot()));
+}
@@ -0,0 +1,81 @@
+mod config;
+
+use anyhow::{anyhow, Context, Error, Severity};
+use std::io;
+
+fn error() -> Error {
+    let io_error = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    Err::<(), _>(io_error)
+        .context("f failed")
+        .context("g failed")
+        .unwrap_err()
+}
+
+#[test]
+fn test_snapshot_debug() {
+    let _config = config::set(config::plain());
+
+    let error = error();
+    let snapshot = error.clone_snapshot();
+    assert_eq!(format!("{:?}", error), format!("{:?}", snapshot));
+    assert_eq!(format!("{:#}", error), format!("{:#}", snapshot));
+    assert_eq!(format!("{:+?}", error), format!("{:+?}", snapshot));
+
+    let again = snapshot.clone_snapshot();
+    assert_eq!(format!("{:?}", error), format!("{:?}", again));
+}
+
+#[test]
+fn test_snapshot_metadata() {
+    let _config = config::set(config::plain());
+
+    let error = error()
+        .with_severity(Severity::Warn)
+        .with_code(7)
+        .retryable(true);
+    let snapshot = error.clone_snapshot();
+    assert_eq!(Some(Severity::Warn), snapshot.severity());
+    assert_eq!(Some(7), snapshot.code());
+    assert!(snapshot.is_retryable());
+    assert_eq!(format!("{:?}", error), format!("{:?}", snapshot));
+}
+
+#[test]
+fn test_snapshot_downcast() {
+    let snapshot = error().clone_snapshot();
+    assert_eq!(3, snapshot.chain().count());
+    assert!(snapshot.downcast_ref::<io::Error>().is_none());
+    assert!(!snapshot.chain_contains::<io::Error>());
+
+    let snapshot = anyhow!("oh no!").clone_snapshot();
+    assert_eq!("oh no!", snapshot.to_string());
+}
+
+#[test]
+fn test_from_parts() {
+    let _config = config::set(config::plain().show_backtrace(true));
+
+    let error = anyhow!("connection reset")
+        .context("failed to fetch")
+        .context("failed to sync");
+    let rebuilt = Error::from_parts(error.chain_messages(), error.backtrace_string());
+    assert_eq!(format!("{:?}", error), format!("{:?}", rebuilt));
+    assert_eq!(format!("{:#}", error), format!("{:#}", rebuilt));
+    assert!(!rebuilt.is::<&str>());
+
+    let backtrace = "Stack backtrace:\n   0: worker::main\n             at src/main.rs:4";
+    let rebuilt = Error::from_parts(
+        vec!["failed to sync".to_owned(), "connection reset".to_owned()],
+        Some(backtrace.to_owned()),
+    );
+    let expected = format!(
+        "failed to sync\n\nCaused by:\n    connection reset\n\n{}",
+        backtrace,
+    );
+    assert_eq!(expected, format!("{:?}", rebuilt));
+    assert_eq!(Some(backtrace.to_owned()), rebuilt.backtrace_string());
+    assert_eq!(expected, format!("{:?}", rebuilt.clone_snapshot()));
+
+    let rebuilt = Error::from_parts(Vec::new(), None);
+    assert_eq!("", rebuilt.to_string());
+}
@@ -60,3 +60,18 @@ fn test_anyhow_from_anyhow() {
     let error = anyhow!(error);
     assert_eq!("oh no!", error.source().unwrap().to_string());
 }
+
+#[test]
+fn test_as_ref_dyn() {
+    fn source_message(error: &dyn StdError) -> Option<String> {
+        error.source().map(ToString::to_string)
+    }
+
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = anyhow!(TestError::Io(io)).context("context");
+    let borrowed = error.as_ref_dyn();
+    assert_eq!("context", borrowed.to_string());
+    assert_eq!(Some("oh no!".to_owned()), source_message(borrowed));
+    assert!(borrowed.downcast_ref::<TestError>().is_none());
+    assert_eq!(None, source_message(error.root_cause()));
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "termcolor")]
+
+mod config;
+
+extern crate termcolor_crate as termcolor;
+
+use anyhow::{anyhow, Error};
+use termcolor::Buffer;
+
+fn error() -> Error {
+    anyhow!("oh no!").context("inner").context("outer")
+}
+
+#[test]
+fn test_ansi() {
+    let _config = config::set(config::plain());
+
+    let mut buffer = Buffer::ansi();
+    error().write_colored(&mut buffer).unwrap();
+    let expected = "\
+        \x1b[0m\x1b[1mouter\x1b[0m\n\
+        \n\
+        Caused by:\n    \
+        \x1b[0m\x1b[2m0:\x1b[0m inner\n    \
+        \x1b[0m\x1b[2m1:\x1b[0m oh no!";
+    assert_eq!(expected, String::from_utf8(buffer.into_inner()).unwrap());
+}
+
+#[test]
+fn test_no_color() {
+    let mut buffer = Buffer::no_color();
+    error().write_colored(&mut buffer).unwrap();
+    assert_eq!(error().report().as_bytes(), buffer.as_slice());
+}
+
+#[test]
+fn test_hyperlink_text() {
+    let _config = config::set(config::plain());
+
+    let error = anyhow!("invalid manifest").with_help_url("https://docs.example/E1");
+    let mut buffer = Buffer::ansi();
+    error.write_colored(&mut buffer).unwrap();
+    let expected = "\
+        \x1b[0m\x1b[1minvalid manifest\x1b[0m\n\
+        \n\
+        See: https://docs.example/E1";
+    assert_eq!(expected, String::from_utf8(buffer.into_inner()).unwrap());
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "thread-origin")]
+
+mod config;
+
+use anyhow::{anyhow, Error};
+use std::thread;
+
+fn on_thread<F>(name: &str, f: F) -> Error
+where
+    F: FnOnce() -> Error + Send + 'static,
+{
+    thread::Builder::new()
+        .name(name.to_owned())
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn test_origin_thread() {
+    let error = on_thread("worker-2", || anyhow!("oh no!"));
+    assert_eq!(Some("worker-2"), error.origin_thread());
+
+    // Adding context elsewhere keeps the thread the error was created on.
+    let error = on_thread("worker-3", move || error.context("context"));
+    assert_eq!(Some("worker-2"), error.origin_thread());
+    assert_eq!(Some("worker-2"), error.clone_snapshot().origin_thread());
+
+    let error = thread::spawn(|| anyhow!("oh no!")).join().unwrap();
+    assert!(error.origin_thread().unwrap().starts_with("ThreadId("));
+
+    let _config = config::set(config::plain());
+    let error = on_thread("worker-2", || anyhow!("root").context("outer"));
+    assert_eq!("outer\n\nCaused by:\n    root", format!("{:?}", error));
+    anyhow::set_format_config(config::plain().show_thread(true));
+    assert_eq!(
+        "outer (thread: worker-2)\n\nCaused by:\n    root",
+        format!("{:?}", error),
+    );
+}
@@ -0,0 +1,21 @@
+#![cfg(feature = "timestamp")]
+
+use anyhow::anyhow;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_created_at() {
+    let before = SystemTime::now();
+    let error = anyhow!("oh no!");
+    let after = SystemTime::now();
+
+    let created_at = error.created_at().unwrap();
+    assert!(before <= created_at && created_at <= after);
+
+    // Adding context later keeps the time the error was created.
+    thread::sleep(Duration::from_millis(10));
+    let error = error.context("context");
+    assert_eq!(Some(created_at), error.created_at());
+    assert_eq!(Some(created_at), error.clone_snapshot().created_at());
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "track-caller")]
+// io::Error::other is newer than the minimum supported Rust version.
+#![allow(clippy::io_other_error)]
+
+mod config;
+
+use anyhow::{anyhow, bail, ensure, Context, Error, FormatConfig, Result};
+use std::io;
+
+#[test]
+fn test_anyhow() {
+    let _config = config::set(config::plain().show_creation_location(true));
+    let line = line!() + 1;
+    let error = anyhow!("oh no!");
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!("oh no!", error.to_string());
+
+    let line = line!() + 1;
+    let error = anyhow!("{} no!", "oh");
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", error));
+
+    let line = line!() + 1;
+    let error = anyhow!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_bail() {
+    let _config = config::set(config::plain().show_creation_location(true));
+    let line = line!() + 2;
+    fn f() -> Result<()> {
+        bail!("oh no!");
+    }
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", f().unwrap_err()));
+
+    let line = line!() + 2;
+    fn g() -> Result<()> {
+        ensure!(1 + 1 == 3, "oh no!");
+        Ok(())
+    }
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", g().unwrap_err()));
+}
+
+#[test]
+fn test_context() {
+    let _config = config::set(config::plain().show_creation_location(true));
+    let line = line!() + 1;
+    let error = anyhow!("oh no!").context("context");
+    let expected = format!(
+        "context\n\nCaused by:\n    oh no! (at tests/test_track_caller.rs:{})",
+        line,
+    );
+    assert_eq!(expected, format!("{:?}", error));
+
+    // Wrapping it again keeps the original location.
+    let error = anyhow!(error);
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_snapshot() {
+    let _config = config::set(config::plain().show_creation_location(true));
+    let line = line!() + 1;
+    let error = anyhow!("oh no!").context("context");
+    let expected = format!(
+        "context\n\nCaused by:\n    oh no! (at tests/test_track_caller.rs:{})",
+        line,
+    );
+    assert_eq!(expected, format!("{:?}", error.clone_snapshot()));
+}
+
+#[test]
+fn test_not_macro() {
+    let _config = config::set(config::plain().show_creation_location(true));
+    let error = Error::msg("oh no!");
+    assert_eq!("oh no!", format!("{:?}", error));
+
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let error = result.context("context").unwrap_err();
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+}
+
+#[test]
+fn test_hidden() {
+    let _config = config::set(config::plain().show_creation_location(false));
+    let line = line!() + 1;
+    let error = anyhow!("oh no!").context("context");
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+
+    // The location is recorded all the same.
+    anyhow::set_format_config(config::plain().show_creation_location(true));
+    let expected = format!(
+        "context\n\nCaused by:\n    oh no! (at tests/test_track_caller.rs:{})",
+        line,
+    );
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_shown_by_default() {
+    let _config = config::set(FormatConfig::new().show_backtrace(false));
+    let line = line!() + 1;
+    let error = anyhow!("oh no!");
+    let expected = format!("oh no! (at tests/test_track_caller.rs:{})", line);
+    assert_eq!(expected, format!("{:?}", error));
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "wasm")]
+
+mod config;
+
+use anyhow::{anyhow, Context};
+use std::io;
+
+// A single test, since whether backtraces are captured is decided once per
+// process from the environment.
+#[test]
+fn test_no_backtrace() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let _config = config::set(config::plain().show_backtrace(true));
+
+    let error = anyhow!("oh no!").context("context").ensure_backtrace();
+    assert_eq!(None, error.backtrace_string());
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+
+    let result: Result<(), _> = Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let error = result.context_capture("context").unwrap_err();
+    assert_eq!(None, error.backtrace_string());
+    assert_eq!("context\n\nCaused by:\n    oh no!", format!("{:?}", error));
+}
@@ -0,0 +1,30 @@
+use anyhow::{bail, Result, Severity};
+use std::io;
+
+fn literal() -> Result<()> {
+    bail!("oh no!");
+}
+
+fn fmt() -> Result<()> {
+    bail!("{} {}!", "oh", "no");
+}
+
+fn error() -> Result<()> {
+    bail!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+}
+
+fn retryable() -> Result<()> {
+    bail!(retryable: true, "oh {}!", "no");
+}
+
+fn severity() -> Result<()> {
+    bail!(severity: Severity::Warn, io::Error::new(io::ErrorKind::Other, "oh no!"));
+}
+
+fn both() -> Result<()> {
+    bail!(severity: Severity::Warn, retryable: true, "oh no!",);
+}
+
+fn main() {
+    let _ = (literal(), fmt(), error(), retryable(), severity(), both());
+}
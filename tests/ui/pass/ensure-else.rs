@@ -0,0 +1,31 @@
+use anyhow::{ensure, Result};
+use std::io;
+
+fn literal(v: i32) -> Result<()> {
+    ensure!(v == 0, "oh no!");
+    Ok(())
+}
+
+fn fmt(v: i32) -> Result<()> {
+    ensure!(v == 0, "oh {}!", "no");
+    Ok(())
+}
+
+fn error(v: i32) -> Result<()> {
+    ensure!(v == 0, io::Error::new(io::ErrorKind::Other, "oh no!"));
+    Ok(())
+}
+
+fn else_error(v: i32) -> Result<()> {
+    ensure!(v == 0, else => io::Error::new(io::ErrorKind::Other, "oh no!"));
+    Ok(())
+}
+
+fn else_typed(v: i32) -> Result<(), io::Error> {
+    ensure!(v == 0, else => io::Error::new(io::ErrorKind::Other, "oh no!"),);
+    Ok(())
+}
+
+fn main() {
+    let _ = (literal(1), fmt(1), error(1), else_error(1), else_typed(1));
+}
@@ -0,0 +1,24 @@
+use anyhow::{ensure_eq, ensure_ne, Result};
+
+#[derive(PartialEq, Debug)]
+struct Version(u32);
+
+fn eq(v: &Version) -> Result<()> {
+    ensure_eq!(*v, Version(1));
+    ensure_eq!(v, &Version(1),);
+    ensure_eq!(v.0, 1, "oh no!");
+    ensure_eq!(v.0, 1, "oh {}!", "no");
+    Ok(())
+}
+
+fn ne(v: &Version) -> Result<()> {
+    ensure_ne!(*v, Version(0));
+    ensure_ne!(v, &Version(0),);
+    ensure_ne!(v.0, 0, "oh no!");
+    ensure_ne!(v.0, 0, "oh {}!", "no",);
+    Ok(())
+}
+
+fn main() {
+    let _ = (eq(&Version(1)), ne(&Version(1)));
+}